@@ -11,7 +11,7 @@ fn main() ->Res<()> {
     client_socket.set_broadcast(true)?;
 
     // 打印本地客户端地址
-    println!("Client = {}",client_socket.local_addr()?.ip().to_string());
+    println!("Client = {}",client_socket.local_addr()?.ip());
 
     // 采用命令行接收数据并发放给服务器
     let mut buffer = String::new();
@@ -17,7 +17,7 @@ fn main() ->Res<()> {
     client_socket.connect(unicast_address)?;
 
     // 打印本地客户端地址
-    println!("Client = {}",client_socket.local_addr()?.ip().to_string());
+    println!("Client = {}",client_socket.local_addr()?.ip());
 
     // 采用命令行接收数据并发放给服务器
     let mut buffer = String::new();
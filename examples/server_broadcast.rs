@@ -11,7 +11,7 @@ fn main() -> Res<()> {
     let broadcast_socket = UdpSocket::bind(broadcast_address)?;
 
     // 确定启动地址
-    println!("Server = {}",broadcast_address.ip().to_string());
+    println!("Server = {}",broadcast_address.ip());
 
     // 设置缓冲区
     let mut buffer = [0;1024];
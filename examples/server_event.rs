@@ -0,0 +1,38 @@
+use aqara_rs::prelude::{Res, MessageEvent};
+use aqara_rs::device::{Gateway, Peer};
+use aqara_rs::protocol::{Frame, Iam, Message};
+use aqara_rs::types::Sid;
+
+struct Logger;
+
+impl MessageEvent for Logger{
+
+    fn on_iam(&self,peer:&Peer,iam:&Iam){
+        println!("[iam] {} = {:?} | version = {:?}",peer.ip,iam.model,peer.version);
+    }
+
+    fn on_report(&self,peer:&Peer,frame:&Frame){
+        println!("[report] {} | {} = {:?}",peer.ip,frame.sid,frame.data);
+    }
+
+    fn on_heartbeat(&self,peer:&Peer,frame:&Frame){
+        println!("[heartbeat] {} | {} = {:?}",peer.ip,frame.sid,frame.data);
+    }
+
+    fn on_ack(&self,peer:&Peer,message:&Message){
+        println!("[ack] {} | {:?}",peer.ip,message);
+    }
+
+    fn on_join(&self,peer:&Peer,sid:&Sid){
+        println!("[join] {} | {}",peer.ip,sid);
+    }
+}
+
+fn main() -> Res<()> {
+
+    let server = Gateway::with_capacity(1024)?;
+    server.discover()?;
+    server.run_event(Box::new(Logger))?;
+
+    Ok(())
+}
@@ -17,7 +17,7 @@ fn main() -> Res<()> {
     )?;
 
     // 确定启动地址
-    println!("Server = {}",multicast_address.ip().to_string());
+    println!("Server = {}",multicast_address.ip());
 
     // 设置缓冲区
     let mut buffer = [0;1024];
@@ -11,7 +11,7 @@ fn main() -> Res<()> {
     let unicast_socket = UdpSocket::bind(unicast_address)?;
 
     // 确定启动地址
-    println!("Server = {}",unicast_address.ip().to_string());
+    println!("Server = {}",unicast_address.ip());
 
     // 设置缓冲区
     let mut buffer = [0;1024];
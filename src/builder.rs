@@ -1,7 +1,8 @@
 extern crate crypto;
 
 use self::crypto::buffer::{BufferResult, WriteBuffer, ReadBuffer};
-use crate::prelude::{INITIALIZE_AES_KEY_IV, MESSAGE_BUFF_SIZE};
+use crate::prelude::{AES_KEY_SIZE, INITIALIZE_AES_KEY_IV, MESSAGE_BUFF_SIZE};
+use crate::types::Token;
 
 
 ///
@@ -25,6 +26,11 @@ impl KeyBuilder{
     ///
     pub fn encode(key:&[u8],token:&[u8])->Result<Vec<u8>,crypto::symmetriccipher::SymmetricCipherError>{
 
+        // 长度不对的时候 AES 内部会直接 panic, 这里提前拦截
+        if key.len() != AES_KEY_SIZE || token.len() != AES_KEY_SIZE {
+            return Err(crypto::symmetriccipher::SymmetricCipherError::InvalidLength);
+        }
+
         // 生成通用 Encryptor, 这里不需要填充, 直接获取 16 位字节
         let mut encryptor = crypto::aes::cbc_encryptor(
             crypto::aes::KeySize::KeySize128,
@@ -44,7 +50,7 @@ impl KeyBuilder{
         // 循环分析数据
         loop {
             let result = encryptor.encrypt(&mut buffer_read,&mut buffer_write,true)?;
-            result_final.extend(buffer_write.take_read_buffer().take_remaining().iter().map(|&i| i));
+            result_final.extend(buffer_write.take_read_buffer().take_remaining().iter().copied());
             match result {
                 BufferResult::BufferUnderflow => break,
                 BufferResult::BufferOverflow => { },
@@ -54,12 +60,12 @@ impl KeyBuilder{
     }
 
     ///
-    /// 将字节位转化成 ASCII 字符串
+    /// 将字节位转化成 ASCII 字符串, 每个字节固定两位, 不足补 0
     ///
-    pub fn hex2dex(hex:&Vec<u8>)->String{
+    pub fn hex2dex(hex:&[u8])->String{
         let mut dex = String::new();
         for x in hex.iter() {
-            dex.push_str(format!("{:X}",x).as_str());
+            dex.push_str(format!("{:02X}",x).as_str());
         }
         dex
     }
@@ -82,4 +88,17 @@ impl KeyBuilder{
         let buf = Self::encode(gateway_key.as_bytes(),token.as_bytes())?;
         Ok(Self::hex2dex(&buf))
     }
+
+    ///
+    /// 使用网关密码对心跳 token 签名, 生成写入命令需要的 `key`
+    ///
+    /// ```
+    /// use aqara_rs::builder::KeyBuilder;
+    /// let token = "1234567890abcdef".parse().unwrap();
+    /// assert_eq!(KeyBuilder::sign("0987654321qwerty",&token).unwrap(),"3EB43E37C20AFF4C5872CC0D04D81314");
+    /// ```
+    ///
+    pub fn sign(gateway_key:&str,token:&Token)->Result<String,crypto::symmetriccipher::SymmetricCipherError>{
+        Self::encode_str(gateway_key,token.as_str())
+    }
 }
@@ -27,10 +27,396 @@
 //! 以此架构可以反推如何构建出网关服务
 //!
 
-use crate::prelude::{DEFAULT_MULTICAST_ADDRESS, DEFAULT_MULTICAST_PORT, Res, DEFAULT_UNICAST_ADDRESS, DEFAULT_UNICAST_PORT, ResponseEvent};
+use crate::prelude::{DEFAULT_MULTICAST_ADDRESS, DEFAULT_MULTICAST_PORT, Res, DEFAULT_UNICAST_ADDRESS, DEFAULT_UNICAST_PORT, GATEWAY_PAIRING_WINDOW, ResponseEvent, MessageEvent};
 use crate::session::{Multicast, Unicast};
-use std::net::Ipv4Addr;
-use std::sync::Arc;
+use crate::protocol::{AckError, Frame, Message, Version, WriteCommand};
+use crate::types::{Model, Sid, Token};
+use crate::devices::Hub;
+use std::net::{Ipv4Addr, IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex, RwLock};
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+///
+/// 已发现的网关信息, 由网关应答 `whois` 的 `iam` 报文生成
+///
+/// 尚未应答 `iam` 的网关没有型号, 只有收到网关心跳之后才能确定 sid
+///
+#[derive(Debug,Clone,PartialEq)]
+pub struct Peer{
+    pub sid:Option<Sid>,
+    pub ip:IpAddr,
+    pub port:u16,
+    pub model:Option<Model>,
+    pub version:Version,
+}
+
+impl Peer{
+    ///
+    /// 网关单播通讯地址
+    ///
+    pub fn addr(&self)->SocketAddr{
+        SocketAddr::new(self.ip,self.port)
+    }
+}
+
+
+///
+/// 网关运行时共享的状态, 收发线程之间通过 Arc 共享
+///
+#[derive(Default)]
+struct Registry{
+    versions:RwLock<HashMap<IpAddr,Version>>, // 手动指定的协议版本
+    peers:RwLock<HashMap<IpAddr,Peer>>, // 自动发现的网关
+    tokens:RwLock<HashMap<IpAddr,(Sid,Token)>>, // 网关最新的 (sid, token)
+    passwords:RwLock<HashMap<Sid,String>>, // 网关 sid 对应的密码
+    devices:RwLock<HashMap<Sid,IpAddr>>, // 子设备 sid 所属的网关
+    pending:Mutex<HashMap<Sid,VecDeque<(Instant,WriteCommand)>>>, // 按发送顺序等待应答的写入命令
+    retries:Mutex<Vec<(IpAddr,WriteCommand)>>, // 等待网关下一次心跳之后重试的写入命令
+    members:RwLock<HashMap<IpAddr,Vec<Sid>>>, // 网关最近一次 `get_id_list_ack` 的子设备列表
+    pairing:RwLock<HashMap<IpAddr,Pairing>>, // 网关的配对窗口
+    joined:Mutex<Vec<(IpAddr,Sid)>>, // 配对窗口内新入网, 还没有取走的子设备
+}
+
+///
+/// 写入命令等待 `write_ack` 的时长, 超时之后不再匹配应答
+///
+const WRITE_ACK_TIMEOUT:Duration = Duration::from_secs(10);
+
+///
+/// 网关配对窗口剩余不足这个时长时, 在心跳之后重新写入 `join_permission`( 网关心跳间隔为 10 秒 )
+///
+const PAIRING_RENEW_MARGIN:Duration = Duration::from_secs(15);
+
+///
+/// 配对窗口, 网关自身的窗口固定为 `GATEWAY_PAIRING_WINDOW`, 请求的时长更长时需要在网关窗口结束之前续期
+///
+struct Pairing{
+    deadline:Instant, // 请求的截止时间
+    window:Instant, // 网关当前窗口的截止时间
+    command:WriteCommand, // 续期使用的 `join_permission` 命令
+}
+
+impl Pairing{
+    fn is_open(&self,now:Instant)->bool{
+        now < self.deadline.max(self.window)
+    }
+
+    ///
+    /// 是否需要续期网关窗口
+    ///
+    fn needs_renewal(&self,now:Instant)->bool{
+        now < self.deadline && self.window < self.deadline && self.window.saturating_duration_since(now) < PAIRING_RENEW_MARGIN
+    }
+}
+
+///
+/// 收到报文之后需要网关继续处理的动作
+///
+enum Follow{
+    Discover, // 发送 whois 查询网关
+    Resend(WriteCommand), // 使用新的 token 重新写入
+}
+
+impl Registry{
+
+    fn version(&self,ip:IpAddr)->Version{
+        if let Some(version) = self.versions.read().ok().and_then(|versions| versions.get(&ip).copied()) {
+            return version;
+        }
+        self.peer(ip).map(|peer| peer.version).unwrap_or_default()
+    }
+
+    fn peer(&self,ip:IpAddr)->Option<Peer>{
+        self.peers.read().ok().and_then(|peers| peers.get(&ip).cloned())
+    }
+
+    fn peer_by_sid(&self,sid:&Sid)->Option<Peer>{
+        self.peers.read().ok().and_then(|peers| peers.values().find(|peer| peer.sid.as_ref() == Some(sid)).cloned())
+    }
+
+    ///
+    /// 确认报文来源的网关, 尚未应答 `iam` 的网关只能确定地址和协议版本
+    ///
+    fn identify(&self,message:Option<&Message>,src:SocketAddr)->Peer{
+        if let Some(peer) = self.peer(src.ip()) {
+            return peer;
+        }
+        let sid = match message {
+            Some(Message::Heartbeat(frame)) if frame.token.is_some() => Some(frame.sid.clone()),
+            _ => self.token(src.ip()).map(|(sid,_)| sid),
+        };
+        Peer{
+            sid,
+            ip: src.ip(),
+            port: src.port(),
+            model: None,
+            version: self.version(src.ip()),
+        }
+    }
+
+    fn token(&self,ip:IpAddr)->Option<(Sid,Token)>{
+        self.tokens.read().ok().and_then(|tokens| tokens.get(&ip).cloned())
+    }
+
+    fn locate(&self,sid:&Sid)->Option<IpAddr>{
+        if let Some(peer) = self.peer_by_sid(sid) {
+            return Some(peer.ip);
+        }
+        self.devices.read().ok().and_then(|devices| devices.get(sid).copied())
+    }
+
+    fn record_token(&self,ip:IpAddr,sid:&Sid,token:&Token){
+        if let Ok(mut tokens) = self.tokens.write() {
+            tokens.insert(ip,(sid.clone(),*token));
+        }
+    }
+
+    fn record_device(&self,sid:&Sid,ip:IpAddr){
+        if let Ok(mut devices) = self.devices.write() {
+            devices.insert(sid.clone(),ip);
+        }
+    }
+
+    fn forget_device(&self,sid:&Sid){
+        if let Ok(mut devices) = self.devices.write() {
+            devices.remove(sid);
+        }
+        if let Ok(mut members) = self.members.write() {
+            for list in members.values_mut() {
+                list.retain(|member| member != sid);
+            }
+        }
+    }
+
+    fn is_pairing(&self,ip:IpAddr)->bool{
+        self.pairing.read().ok()
+            .map_or(false,|pairing| pairing.get(&ip).map_or(false,|pairing| pairing.is_open(Instant::now())))
+    }
+
+    ///
+    /// 配对窗口内对比网关的子设备列表, 记录列表之外的新设备
+    ///
+    /// 还没有收到过子设备列表时无法区分新旧设备, 所以打开配对窗口时会先查询一次列表
+    ///
+    fn detect(&self,ip:IpAddr,devices:&[Sid]){
+        if !self.is_pairing(ip) {
+            return;
+        }
+        let gateway = self.token(ip).map(|(sid,_)| sid);
+        let mut found = Vec::new();
+        if let Ok(mut members) = self.members.write() {
+            if let Some(list) = members.get_mut(&ip) {
+                for sid in devices {
+                    if !list.contains(sid) && gateway.as_ref() != Some(sid) {
+                        list.push(sid.clone());
+                        found.push((ip,sid.clone()));
+                    }
+                }
+            }
+        }
+        if let Ok(mut joined) = self.joined.lock() {
+            joined.extend(found);
+        }
+    }
+
+    fn detect_frame(&self,ip:IpAddr,frame:&Frame){
+        // 带 token 的心跳是网关自身
+        if frame.token.is_none() {
+            self.detect(ip,std::slice::from_ref(&frame.sid));
+        }
+    }
+
+    fn take_joined(&self,ip:Option<IpAddr>)->Vec<Sid>{
+        let mut joined = match self.joined.lock() {
+            Ok(joined) => joined,
+            Err(_) => return Vec::new(),
+        };
+        let (taken,rest):(Vec<_>,Vec<_>) = joined.drain(..).partition(|(from,_)| ip.map_or(true,|ip| ip == *from));
+        *joined = rest;
+        taken.into_iter().map(|(_,sid)| sid).collect()
+    }
+
+    ///
+    /// 按照目标网关的协议版本编码报文, 并通过单播推送
+    ///
+    fn send(&self,unicast:&Unicast,message:&Message,target:SocketAddr)->Res<usize>{
+        let buf = message.to_bytes_with(self.version(target.ip()));
+        unicast.send_to(buf.as_slice(),target)
+    }
+
+    fn write(&self,unicast:&Unicast,command:&WriteCommand)->Res<usize>{
+        let ip = self.locate(command.sid()).ok_or_else(||
+            std::io::Error::new(std::io::ErrorKind::NotFound,format!("unknown device `{}`",command.sid()))
+        )?;
+        let (gateway_sid,token) = self.token(ip).ok_or_else(||
+            std::io::Error::new(std::io::ErrorKind::NotFound,format!("no token from gateway {}",ip))
+        )?;
+        let password = self.passwords.read().ok()
+            .and_then(|passwords| passwords.get(&gateway_sid).cloned())
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound,format!("no password for gateway `{}`",gateway_sid)))?;
+        let message = command.sign(&password,&token).map_err(|e|
+            std::io::Error::new(std::io::ErrorKind::InvalidData,format!("{:?}",e))
+        )?;
+        let target = self.peer(ip).map(|peer| peer.addr()).unwrap_or_else(|| SocketAddr::new(ip,DEFAULT_UNICAST_PORT));
+        // 先登记再发送, 否则应答可能在登记之前就被接收线程处理
+        self.track(command);
+        let result = self.send(unicast,&message,target);
+        if result.is_err() {
+            self.untrack(command.sid());
+        }
+        result
+    }
+
+    ///
+    /// 登记等待应答的写入命令, 同一个 sid 的应答按照发送顺序依次匹配
+    ///
+    fn track(&self,command:&WriteCommand){
+        if let Ok(mut pending) = self.pending.lock() {
+            let now = Instant::now();
+            pending.retain(|_,queue| {
+                queue.retain(|(sent,_)| now.saturating_duration_since(*sent) < WRITE_ACK_TIMEOUT);
+                !queue.is_empty()
+            });
+            pending.entry(command.sid().clone()).or_default().push_back((now,command.clone()));
+        }
+    }
+
+    ///
+    /// 撤销最近一次登记的命令( 发送失败 )
+    ///
+    fn untrack(&self,sid:&Sid){
+        if let Ok(mut pending) = self.pending.lock() {
+            if let Some(queue) = pending.get_mut(sid) {
+                queue.pop_back();
+                if queue.is_empty() {
+                    pending.remove(sid);
+                }
+            }
+        }
+    }
+
+    ///
+    /// 取出应答对应的写入命令, 跳过已经超时的命令
+    ///
+    fn acknowledge(&self,sid:&Sid)->Option<WriteCommand>{
+        let mut pending = self.pending.lock().ok()?;
+        let queue = pending.get_mut(sid)?;
+        let now = Instant::now();
+        let mut command = None;
+        while let Some((sent,next)) = queue.pop_front() {
+            if now.saturating_duration_since(sent) < WRITE_ACK_TIMEOUT {
+                command = Some(next);
+                break;
+            }
+        }
+        if queue.is_empty() {
+            pending.remove(sid);
+        }
+        command
+    }
+
+    ///
+    /// 处理报文带来的后续动作
+    ///
+    fn follow(&self,unicast:&Unicast,follows:Vec<Follow>){
+        for follow in follows {
+            let result = match follow {
+                Follow::Discover => unicast.send_to(
+                    Message::whois().to_bytes().as_slice(),
+                    SocketAddr::from((DEFAULT_MULTICAST_ADDRESS,DEFAULT_MULTICAST_PORT))
+                ),
+                Follow::Resend(command) => self.write(unicast,&command),
+            };
+            if let Err(e) = result {
+                eprintln!("{:?}",e);
+            }
+        }
+    }
+
+    ///
+    /// 记录报文带来的网关信息, 返回需要继续处理的动作
+    ///
+    fn observe(&self,message:&Message,src:SocketAddr)->Vec<Follow>{
+        match message {
+            Message::GetIdListAck(list) => {
+                self.record_token(src.ip(),&list.sid,&list.token);
+                for sid in list.devices.iter() {
+                    self.record_device(sid,src.ip());
+                }
+                self.detect(src.ip(),&list.devices);
+                if let Ok(mut members) = self.members.write() {
+                    members.insert(src.ip(),list.devices.clone());
+                }
+                Vec::new()
+            },
+            Message::WriteAck(frame) => {
+                self.record_device(&frame.sid,src.ip());
+                match (self.acknowledge(&frame.sid),frame.error()) {
+                    // token 已经过期, 等待下一次心跳带来新的 token 之后只重试一次
+                    (Some(command),Some(AckError::InvalidKey)) if command.retry_enabled() => {
+                        if let Ok(mut retries) = self.retries.lock() {
+                            retries.push((src.ip(),command.retry(false)));
+                        }
+                    },
+                    // 网关确认移除之后才从本地记录中删除子设备
+                    (Some(command),None) => {
+                        if let Some(sid) = command.data().get_str("remove_device").and_then(|sid| sid.parse().ok()) {
+                            self.forget_device(&sid);
+                        }
+                    },
+                    _ => {},
+                }
+                Vec::new()
+            },
+            Message::Report(frame) | Message::ReadAck(frame) => {
+                self.record_device(&frame.sid,src.ip());
+                self.detect_frame(src.ip(),frame);
+                Vec::new()
+            },
+            Message::Iam(iam) => {
+                let peer = Peer{
+                    sid: Some(iam.sid.clone()),
+                    ip: iam.ip.parse().unwrap_or_else(|_| src.ip()),
+                    port: iam.port,
+                    model: Some(iam.model.clone()),
+                    version: iam.proto_version.as_deref().and_then(Version::from_proto).unwrap_or_default(),
+                };
+                if let Ok(mut peers) = self.peers.write() {
+                    peers.insert(peer.ip,peer);
+                }
+                Vec::new()
+            },
+            // 只有网关心跳带有 token, 没见过的网关需要重新查询
+            Message::Heartbeat(frame) => {
+                self.record_device(&frame.sid,src.ip());
+                self.detect_frame(src.ip(),frame);
+                let mut follows = Vec::new();
+                if let Some(token) = &frame.token {
+                    self.record_token(src.ip(),&frame.sid,token);
+                    if self.peer(src.ip()).is_none() {
+                        follows.push(Follow::Discover);
+                    }
+                    if let Ok(mut retries) = self.retries.lock() {
+                        let (ready,waiting):(Vec<_>,Vec<_>) = retries.drain(..).partition(|(ip,_)| *ip == src.ip());
+                        *retries = waiting;
+                        follows.extend(ready.into_iter().map(|(_,command)| Follow::Resend(command)));
+                    }
+                    if let Ok(mut pairing) = self.pairing.write() {
+                        let now = Instant::now();
+                        if let Some(pairing) = pairing.get_mut(&src.ip()).filter(|pairing| pairing.needs_renewal(now)) {
+                            pairing.window = now + GATEWAY_PAIRING_WINDOW;
+                            follows.push(Follow::Resend(pairing.command.clone()));
+                        }
+                    }
+                }
+                follows
+            },
+            _ => Vec::new(),
+        }
+    }
+}
+
 
 ///
 /// 网关构建器
@@ -42,10 +428,14 @@ use std::sync::Arc;
 /// * unicast_address: 本机单播接收网关服务的地址, 一般可以留空, 只有在设备支持多网络环境的时候才需要
 /// * unicast_port: 本机单播接收网关服务的端口, 一般默认为 `9898`
 ///
+/// 局域网内可能同时存在 1.x 和 2.x 协议的网关, 网关应答 `iam` 时会根据 `proto_version` 记录协议版本,
+/// 之后推送给该网关的命令会自动按照对应的协议编码; 收到未知网关的心跳时会自动发送 `whois` 查询
+///
 pub struct Gateway{
     multicast:Multicast,
     unicast:Arc<Unicast>,
-    capacity:usize
+    capacity:usize,
+    registry:Arc<Registry>,
 }
 
 impl Gateway {
@@ -60,7 +450,166 @@ impl Gateway {
         let unicast = Unicast::create(
             DEFAULT_UNICAST_ADDRESS,
             DEFAULT_UNICAST_PORT)?;
-        Ok(Self{multicast,unicast:Arc::new(unicast),capacity})
+        Ok(Self{
+            multicast,
+            unicast:Arc::new(unicast),
+            capacity,
+            registry:Arc::new(Registry::default()),
+        })
+    }
+
+    ///
+    /// 手动指定网关使用的协议版本, 优先于自动识别的版本
+    ///
+    pub fn set_version(&self,ip:IpAddr,version:Version){
+        if let Ok(mut versions) = self.registry.versions.write() {
+            versions.insert(ip,version);
+        }
+    }
+
+    ///
+    /// 获取指定网关使用的协议版本
+    ///
+    pub fn version(&self,ip:IpAddr)->Version{
+        self.registry.version(ip)
+    }
+
+    ///
+    /// 根据 IP 获取已发现的网关
+    ///
+    pub fn peer(&self,ip:IpAddr)->Option<Peer>{
+        self.registry.peer(ip)
+    }
+
+    ///
+    /// 根据 sid 获取已发现的网关
+    ///
+    pub fn peer_by_sid(&self,sid:&Sid)->Option<Peer>{
+        self.registry.peer_by_sid(sid)
+    }
+
+    ///
+    /// 获取所有已发现的网关
+    ///
+    pub fn peers(&self)->Vec<Peer>{
+        self.registry.peers.read()
+            .map(|peers| peers.values().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    ///
+    /// 设置网关密码, 即米家 APP 局域网通信协议中显示的密码, 用于写入命令签名
+    ///
+    pub fn set_password(&self,sid:&Sid,password:&str){
+        if let Ok(mut passwords) = self.registry.passwords.write() {
+            passwords.insert(sid.clone(),password.to_string());
+        }
+    }
+
+    ///
+    /// 获取网关最新心跳的 token
+    ///
+    pub fn token(&self,ip:IpAddr)->Option<Token>{
+        self.registry.token(ip).map(|(_,token)| token)
+    }
+
+    ///
+    /// 查找设备所属网关的 IP, 网关自身的 sid 也可以查询
+    ///
+    pub fn locate(&self,sid:&Sid)->Option<IpAddr>{
+        self.registry.locate(sid)
+    }
+
+    ///
+    /// 推送写入命令
+    ///
+    /// 根据设备 sid 找到所属网关, 使用该网关的密码和最新心跳 token 签名之后按照网关协议版本推送;
+    /// 命令开启 `retry` 时, 如果网关应答 `Invalid key` 会在下一次心跳更新 token 之后自动重试一次
+    ///
+    pub fn write(&self,command:&WriteCommand)->Res<usize>{
+        self.registry.write(&self.unicast,command)
+    }
+
+    ///
+    /// 打开网关的配对窗口, 允许新的子设备入网
+    ///
+    /// 写入 `join_permission` 之后会重新查询网关的子设备列表, 窗口期内 `get_id_list_ack` 或者上报中出现的新 sid
+    /// 可以通过 `joined` 取走, 使用 `run_event` 时还会回调 `MessageEvent::on_join`
+    ///
+    /// 网关自身的配对窗口固定为 `GATEWAY_PAIRING_WINDOW`: `duration` 更短时本地窗口仍然保持到网关窗口结束,
+    /// 避免漏掉之后入网的设备; `duration` 更长时会在网关窗口结束之前随心跳重新写入 `join_permission`
+    ///
+    pub fn permit_join(&self,gateway:&Sid,duration:Duration)->Res<usize>{
+        let ip = self.registry.locate(gateway).ok_or_else(||
+            std::io::Error::new(std::io::ErrorKind::NotFound,format!("unknown gateway `{}`",gateway))
+        )?;
+        let peer = self.registry.peer(ip);
+        let model = peer.as_ref().and_then(|peer| peer.model.clone()).unwrap_or_else(|| "gateway".parse().unwrap());
+        let command = Hub::new(gateway.clone(),model).permit_join();
+        let sz = self.registry.write(&self.unicast,&command)?;
+        if let Ok(mut pairing) = self.registry.pairing.write() {
+            let now = Instant::now();
+            pairing.insert(ip,Pairing{
+                deadline: now + duration,
+                window: now + GATEWAY_PAIRING_WINDOW,
+                command,
+            });
+        }
+        let target = peer.map(|peer| peer.addr()).unwrap_or_else(|| SocketAddr::new(ip,DEFAULT_UNICAST_PORT));
+        self.registry.send(&self.unicast,&Message::get_id_list(),target)?;
+        Ok(sz)
+    }
+
+    ///
+    /// 网关的配对窗口是否还在打开
+    ///
+    pub fn is_pairing(&self,ip:IpAddr)->bool{
+        self.registry.is_pairing(ip)
+    }
+
+    ///
+    /// 取走配对窗口内新入网的子设备
+    ///
+    pub fn joined(&self)->Vec<Sid>{
+        self.registry.take_joined(None)
+    }
+
+    ///
+    /// 从所属网关移除子设备, 网关应答 `write_ack` 确认之后才会从本地记录中移除
+    ///
+    pub fn remove_device(&self,sid:&Sid)->Res<usize>{
+        let ip = self.registry.locate(sid).ok_or_else(||
+            std::io::Error::new(std::io::ErrorKind::NotFound,format!("unknown device `{}`",sid))
+        )?;
+        let (gateway,_) = self.registry.token(ip).ok_or_else(||
+            std::io::Error::new(std::io::ErrorKind::NotFound,format!("no token from gateway {}",ip))
+        )?;
+        let model = self.registry.peer(ip).and_then(|peer| peer.model).unwrap_or_else(|| "gateway".parse().unwrap());
+        self.registry.write(&self.unicast,&Hub::new(gateway,model).remove_device(sid))
+    }
+
+    ///
+    /// 组播发送 `whois` 查询局域网内的网关
+    ///
+    pub fn discover(&self)->Res<usize>{
+        self.multicast.send(Message::whois().to_bytes().as_slice())
+    }
+
+    ///
+    /// 按照目标网关的协议版本编码报文, 并通过单播推送
+    ///
+    pub fn send(&self,message:&Message,target:SocketAddr)->Res<usize>{
+        self.registry.send(&self.unicast,message,target)
+    }
+
+    ///
+    /// 以协议事件的方式启动服务, 报文会先解析再按照命令分发给 `MessageEvent`
+    ///
+    pub fn run_event(&self, callback:Box<dyn MessageEvent+Sync+Send>) ->Res<()>{
+        self.run(Box::new(Dispatcher{
+            registry:self.registry.clone(),
+            callback,
+        }))
     }
 
     pub fn run(&self, callback:Box<dyn ResponseEvent+Sync+Send>) ->Res<()>{
@@ -70,11 +619,16 @@ impl Gateway {
         let thread_unicast = self.unicast.clone();
         let thread_capacity = self.capacity;
         let thread_cb = cb.clone();
+        let thread_registry = self.registry.clone();
 
         std::thread::spawn(move ||{
             let mut buffer_unicast = vec![0;thread_capacity];
             while let Ok((sz,client)) = thread_unicast.recv_from(buffer_unicast.as_mut_slice()) {
                 if sz > 0 {
+                    if let Ok(message) = Message::parse(&buffer_unicast[..sz]) {
+                        let follows = thread_registry.observe(&message,client);
+                        thread_registry.follow(&thread_unicast,follows);
+                    }
                     let client = thread_unicast.load_client(client).unwrap();
                     thread_cb.join_unicast(
                         buffer_unicast[..sz].to_vec(),
@@ -91,6 +645,10 @@ impl Gateway {
             match self.multicast.recv_from(buffer_multicast.as_mut_slice()) {
                 Ok((sz,client)) => {
                     if sz > 0 {
+                        if let Ok(message) = Message::parse(&buffer_multicast[..sz]) {
+                            let follows = self.registry.observe(&message,client);
+                            self.registry.follow(&self.unicast,follows);
+                        }
                         let client = self.multicast.load_client(client).unwrap();
                         main_cb.join_multicast(buffer_multicast[..sz].to_vec(),client);
                     }
@@ -100,8 +658,241 @@ impl Gateway {
                 }
             }
         }
-        Ok(())
     }
 
 
 }
+
+
+///
+/// 将传输层的 `ResponseEvent` 转换为协议层的 `MessageEvent`
+///
+struct Dispatcher{
+    registry:Arc<Registry>,
+    callback:Box<dyn MessageEvent+Sync+Send>,
+}
+
+impl Dispatcher{
+    fn dispatch(&self,ctx:&[u8],src:Option<SocketAddr>){
+        let src = match src {
+            Some(src) => src,
+            None => return,
+        };
+        match Message::parse(ctx) {
+            Ok(message) => {
+                let peer = self.registry.identify(Some(&message),src);
+                match &message {
+                    Message::Iam(iam) => self.callback.on_iam(&peer,iam),
+                    Message::Report(frame) => self.callback.on_report(&peer,frame),
+                    Message::Heartbeat(frame) => self.callback.on_heartbeat(&peer,frame),
+                    Message::GetIdListAck(_)
+                    | Message::ReadAck(_)
+                    | Message::WriteAck(_) => self.callback.on_ack(&peer,&message),
+                    _ => self.callback.on_message(&peer,&message),
+                }
+                for sid in self.registry.take_joined(Some(src.ip())) {
+                    self.callback.on_join(&peer,&sid);
+                }
+            },
+            Err(e) => self.callback.on_error(&self.registry.identify(None,src),&e),
+        }
+    }
+}
+
+impl ResponseEvent for Dispatcher{
+    fn join_multicast(&self,ctx:Vec<u8>,client:Multicast){
+        self.dispatch(ctx.as_slice(),client.get_client_addr());
+    }
+
+    fn join_unicast(&self,ctx:Vec<u8>,client:Unicast){
+        self.dispatch(ctx.as_slice(),client.get_client_addr());
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::devices::Light;
+
+    const GATEWAY:&str = "34ce00112233";
+
+    fn src()->SocketAddr{
+        SocketAddr::from(([192,168,1,2],DEFAULT_UNICAST_PORT))
+    }
+
+    fn message(raw:&str)->Message{
+        Message::parse(raw.as_bytes()).unwrap()
+    }
+
+    fn heartbeat()->Message{
+        message(r#"{"cmd":"heartbeat","model":"gateway","sid":"34ce00112233","short_id":"0","token":"1234567890abcdef","data":"{\"ip\":\"192.168.1.2\"}"}"#)
+    }
+
+    fn ack(error:Option<&str>)->Message{
+        match error {
+            Some(error) => message(&format!(r#"{{"cmd":"write_ack","model":"gateway","sid":"{}","data":"{{\"error\":\"{}\"}}"}}"#,GATEWAY,error)),
+            None => message(&format!(r#"{{"cmd":"write_ack","model":"gateway","sid":"{}","data":"{{}}"}}"#,GATEWAY)),
+        }
+    }
+
+    fn command(key:&str,value:u32)->WriteCommand{
+        WriteCommand::new(GATEWAY.parse().unwrap(),"gateway".parse().unwrap()).set(key,value).retry(true)
+    }
+
+    fn resent(follows:Vec<Follow>)->Vec<WriteCommand>{
+        follows.into_iter().filter_map(|follow| match follow {
+            Follow::Resend(command) => Some(command),
+            Follow::Discover => None,
+        }).collect()
+    }
+
+    #[test]
+    fn retry_matches_write_order(){
+        let registry = Registry::default();
+        let light = command("rgb",0x64ff0000);
+        let tone = command("mid",10);
+        registry.track(&light);
+        registry.track(&tone);
+
+        // 第一条应答对应第一条写入, 第二条写入不会被覆盖
+        assert!(registry.observe(&ack(Some("Invalid key")),src()).is_empty());
+        assert!(registry.observe(&ack(None),src()).is_empty());
+        assert!(registry.pending.lock().unwrap().is_empty());
+
+        let follows = resent(registry.observe(&heartbeat(),src()));
+        assert_eq!(follows,vec![light.retry(false)]);
+        // 只重试一次
+        assert!(resent(registry.observe(&heartbeat(),src())).is_empty());
+    }
+
+    #[test]
+    fn expired_writes_are_skipped(){
+        let registry = Registry::default();
+        registry.pending.lock().unwrap().entry(GATEWAY.parse().unwrap()).or_default()
+            .push_back((Instant::now() - WRITE_ACK_TIMEOUT,command("rgb",0)));
+        let tone = command("mid",10);
+        registry.track(&tone);
+
+        registry.observe(&ack(Some("Invalid key")),src());
+        assert_eq!(resent(registry.observe(&heartbeat(),src())),vec![tone.retry(false)]);
+    }
+
+    #[test]
+    fn failed_send_is_untracked(){
+        let registry = Registry::default();
+        registry.track(&command("rgb",0));
+        registry.untrack(&GATEWAY.parse().unwrap());
+        assert!(registry.pending.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn removal_waits_for_its_own_ack(){
+        let registry = Registry::default();
+        let hub = Hub::new(GATEWAY.parse().unwrap(),"gateway".parse().unwrap());
+        let first:Sid = "158d0001a2b3c4".parse().unwrap();
+        let second:Sid = "158d0001a2b3c5".parse().unwrap();
+        registry.record_device(&first,src().ip());
+        registry.record_device(&second,src().ip());
+
+        registry.track(&hub.set_light(Light::default()));
+        registry.track(&hub.remove_device(&first));
+        registry.track(&hub.remove_device(&second));
+
+        // 夜灯的应答不会移除设备
+        registry.observe(&ack(None),src());
+        assert!(registry.locate(&first).is_some());
+
+        registry.observe(&ack(None),src());
+        assert!(registry.locate(&first).is_none());
+        assert!(registry.locate(&second).is_some());
+
+        // 失败的移除保留设备
+        registry.observe(&ack(Some("Invalid device")),src());
+        assert!(registry.locate(&second).is_some());
+    }
+
+    fn open_pairing(registry:&Registry,deadline:Duration,window:Duration){
+        let now = Instant::now();
+        let hub = Hub::new(GATEWAY.parse().unwrap(),"gateway".parse().unwrap());
+        registry.pairing.write().unwrap().insert(src().ip(),Pairing{
+            deadline: now + deadline,
+            window: now + window,
+            command: hub.permit_join(),
+        });
+    }
+
+    #[test]
+    fn short_pairing_lasts_gateway_window(){
+        let registry = Registry::default();
+        open_pairing(&registry,Duration::from_secs(0),GATEWAY_PAIRING_WINDOW);
+        assert!(registry.is_pairing(src().ip()));
+        assert!(resent(registry.observe(&heartbeat(),src())).is_empty());
+    }
+
+    #[test]
+    fn long_pairing_is_renewed(){
+        let registry = Registry::default();
+        open_pairing(&registry,Duration::from_secs(90),Duration::from_secs(5));
+        let follows = resent(registry.observe(&heartbeat(),src()));
+        assert_eq!(follows.len(),1);
+        assert_eq!(follows[0].data().get_str("join_permission"),Some("yes"));
+        // 续期之后网关窗口还很长, 下一次心跳不会重复写入
+        assert!(resent(registry.observe(&heartbeat(),src())).is_empty());
+        assert!(registry.is_pairing(src().ip()));
+    }
+
+    #[test]
+    fn iam_records_peer_version(){
+        let registry = Registry::default();
+        let follows = registry.observe(&heartbeat(),src());
+        assert!(matches!(follows.as_slice(),[Follow::Discover]));
+        assert_eq!(registry.version(src().ip()),Version::V1);
+
+        let iam = message(r#"{"cmd":"iam","ip":"192.168.1.2","port":"9898","sid":"34ce00112233","model":"gateway.v3","proto_version":"2.0.1"}"#);
+        assert!(registry.observe(&iam,src()).is_empty());
+        let peer = registry.peer(src().ip()).unwrap();
+        assert_eq!(peer.version,Version::V2);
+        assert_eq!(registry.peer_by_sid(&GATEWAY.parse().unwrap()),Some(peer));
+        assert_eq!(registry.version(src().ip()),Version::V2);
+
+        // 已知的网关心跳不会重复查询
+        assert!(registry.observe(&heartbeat(),src()).is_empty());
+
+        // 手动指定的版本优先
+        registry.versions.write().unwrap().insert(src().ip(),Version::V1);
+        assert_eq!(registry.version(src().ip()),Version::V1);
+    }
+
+    #[test]
+    fn pairing_detects_new_devices(){
+        let registry = Registry::default();
+        registry.observe(&heartbeat(),src());
+        let list = message(r#"{"cmd":"get_id_list_ack","sid":"34ce00112233","token":"1234567890abcdef","data":"[\"158d0001a2b3c4\"]"}"#);
+        let grown = message(r#"{"cmd":"get_id_list_ack","sid":"34ce00112233","token":"1234567890abcdef","data":"[\"158d0001a2b3c4\",\"158d0001a2b3c5\"]"}"#);
+        let report = message(r#"{"cmd":"report","model":"magnet","sid":"158d0001a2b3c6","data":"{\"status\":\"open\"}"}"#);
+        let known = message(r#"{"cmd":"report","model":"magnet","sid":"158d0001a2b3c4","data":"{\"status\":\"open\"}"}"#);
+
+        // 窗口关闭时不记录新设备
+        registry.observe(&list,src());
+        registry.observe(&report,src());
+        assert!(registry.take_joined(None).is_empty());
+
+        open_pairing(&registry,Duration::from_secs(60),GATEWAY_PAIRING_WINDOW);
+        registry.observe(&known,src());
+        registry.observe(&heartbeat(),src());
+        assert!(registry.take_joined(None).is_empty());
+
+        registry.observe(&grown,src());
+        let joined:Vec<Sid> = vec!["158d0001a2b3c5".parse().unwrap()];
+        assert_eq!(registry.take_joined(Some(src().ip())),joined);
+
+        // 上报里新出现的设备同样记录, 只记录一次
+        let other:Sid = "158d0001a2b3c7".parse().unwrap();
+        let report = message(r#"{"cmd":"report","model":"magnet","sid":"158d0001a2b3c7","data":"{\"status\":\"open\"}"}"#);
+        registry.observe(&report,src());
+        registry.observe(&report,src());
+        assert!(registry.take_joined(Some(SocketAddr::from(([192,168,1,3],DEFAULT_UNICAST_PORT)).ip())).is_empty());
+        assert_eq!(registry.take_joined(None),vec![other]);
+    }
+}
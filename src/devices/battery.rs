@@ -0,0 +1,179 @@
+use crate::protocol::Frame;
+use crate::types::{Model, Sid};
+use std::collections::HashMap;
+
+///
+/// 低电量提醒解除的回差, 电量回升到阈值之上这么多才会重新提醒, 避免电压抖动反复触发
+///
+const LOW_BATTERY_HYSTERESIS:u8 = 10;
+
+///
+/// 电池放电曲线
+///
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+pub enum BatteryCurve{
+    Coin, // 纽扣电池 CR2032/CR2450, 3.0V 以上为满电, 2.9V 之后快速下降
+    Linear, // 其他电池按照 2.8V~3.3V 线性估算
+}
+
+impl BatteryCurve{
+    ///
+    /// 根据型号选择放电曲线
+    ///
+    pub fn for_model(model:&Model)->Self{
+        // 使用纽扣电池的型号, 按照包含关系匹配, 同时覆盖 `sensor_` 前缀和 `.aq2` 等版本后缀
+        const COIN:[&str;9] = [
+            "switch", // switch, sensor_switch.aq2/aq3
+            "86sw", // 86sw1/86sw2, sensor_86sw1/sensor_86sw2
+            "magnet", // magnet, sensor_magnet.aq2
+            "sensor_ht", // sensor_ht
+            "weather", // weather.v1
+            "motion", // motion, sensor_motion.aq2
+            "sensor_wleak", // sensor_wleak.aq1
+            "cube", // cube, sensor_cube.aqgl01
+            "vibration", // vibration, sensor_vibration.aq1
+        ];
+        let model = model.as_str();
+        let coin = COIN.iter().any(|pattern| model.contains(pattern));
+        if coin { BatteryCurve::Coin } else { BatteryCurve::Linear }
+    }
+
+    ///
+    /// 电压( mV )转换为估算的电量百分比
+    ///
+    /// ```
+    /// use aqara_rs::devices::BatteryCurve;
+    /// assert_eq!(BatteryCurve::Linear.percentage(3050),50);
+    /// assert_eq!(BatteryCurve::Coin.percentage(3050),100);
+    /// assert_eq!(BatteryCurve::Coin.percentage(2950),71);
+    /// assert_eq!(BatteryCurve::Coin.percentage(2000),0);
+    /// ```
+    ///
+    pub fn percentage(&self,voltage:u32)->u8{
+        let percent = match self {
+            BatteryCurve::Coin => match voltage {
+                v if v >= 3000 => 100,
+                v if v >= 2900 => 100 - (3000 - v) * 58 / 100,
+                v if v >= 2740 => 42 - (2900 - v) * 24 / 160,
+                v if v >= 2440 => 18 - (2740 - v) * 12 / 300,
+                v if v >= 2100 => 6 - (2440 - v) * 6 / 340,
+                _ => 0,
+            },
+            BatteryCurve::Linear => voltage.clamp(2800,3300).saturating_sub(2800) / 5,
+        };
+        percent.min(100) as u8
+    }
+}
+
+///
+/// 电池事件
+///
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub enum BatteryEvent{
+    Level{sid:Sid,voltage:u32,percent:u8}, // 电量百分比变化
+    Low{sid:Sid,percent:u8}, // 电量低于阈值, 每次跌破阈值只提醒一次
+}
+
+///
+/// 电池状态
+///
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub struct BatteryLevel{
+    pub curve:BatteryCurve,
+    pub voltage:u32, // mV
+    pub percent:u8,
+    pub low:bool, // 已经提醒过低电量
+}
+
+///
+/// 所有电池设备共用的电量跟踪
+///
+/// 电池设备在心跳和部分上报中带有 `voltage`, 按照型号的放电曲线换算成电量并记录到对应 sid,
+/// 电量低于阈值时触发一次 `BatteryEvent::Low`, 更换电池之后重新计算
+///
+/// ```
+/// use aqara_rs::protocol::Message;
+/// use aqara_rs::devices::{Battery, BatteryEvent};
+/// let mut battery = Battery::new(20);
+/// if let Message::Heartbeat(frame) = Message::parse(br#"{"cmd":"heartbeat","model":"smoke","sid":"158d0001a2b3c4","data":"{\"voltage\":2880}"}"#).unwrap() {
+///     let sid = frame.sid.clone();
+///     assert_eq!(battery.update(&frame),vec![
+///         BatteryEvent::Level{sid:sid.clone(),voltage:2880,percent:16},
+///         BatteryEvent::Low{sid,percent:16},
+///     ]);
+///     assert!(battery.update(&frame).is_empty());
+/// }
+/// ```
+///
+#[derive(Debug,Clone)]
+pub struct Battery{
+    threshold:u8,
+    levels:HashMap<Sid,BatteryLevel>,
+}
+
+impl Battery{
+    ///
+    /// 指定低电量阈值( 百分比 )
+    ///
+    pub fn new(threshold:u8)->Self{
+        Self{threshold:threshold.min(100),levels:HashMap::new()}
+    }
+
+    pub fn threshold(&self)->u8{
+        self.threshold
+    }
+
+    ///
+    /// 修改低电量阈值, 已经提醒过的设备不会重复提醒
+    ///
+    pub fn set_threshold(&mut self,threshold:u8){
+        self.threshold = threshold.min(100);
+    }
+
+    ///
+    /// 获取设备最近一次的电池状态
+    ///
+    pub fn level(&self,sid:&Sid)->Option<&BatteryLevel>{
+        self.levels.get(sid)
+    }
+
+    ///
+    /// 所有低于阈值的设备
+    ///
+    pub fn low(&self)->Vec<&Sid>{
+        self.levels.iter()
+            .filter(|(_,level)| level.percent < self.threshold)
+            .map(|(sid,_)| sid)
+            .collect()
+    }
+
+    ///
+    /// 读取报文中的电压, 没有型号的报文沿用该设备之前的放电曲线
+    ///
+    pub fn update(&mut self,frame:&Frame)->Vec<BatteryEvent>{
+        let mut events = Vec::new();
+        let voltage = match frame.data.voltage() {
+            Some(voltage) if voltage > 0 => voltage,
+            _ => return events,
+        };
+        let previous = self.levels.get(&frame.sid).copied();
+        let curve = match (&frame.model,previous) {
+            (Some(model),_) => BatteryCurve::for_model(model),
+            (None,Some(level)) => level.curve,
+            (None,None) => BatteryCurve::Linear,
+        };
+        let percent = curve.percentage(voltage);
+        let mut low = previous.map_or(false,|level| level.low);
+        if previous.map_or(true,|level| level.percent != percent) {
+            events.push(BatteryEvent::Level{sid:frame.sid.clone(),voltage,percent});
+        }
+        if percent < self.threshold && !low {
+            low = true;
+            events.push(BatteryEvent::Low{sid:frame.sid.clone(),percent});
+        } else if percent >= self.threshold.saturating_add(LOW_BATTERY_HYSTERESIS) {
+            low = false;
+        }
+        self.levels.insert(frame.sid.clone(),BatteryLevel{curve,voltage,percent,low});
+        events
+    }
+}
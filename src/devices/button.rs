@@ -0,0 +1,134 @@
+use crate::devices::Device;
+use crate::prelude::DeviceStatus;
+use crate::protocol::Frame;
+use crate::types::{Model, Sid};
+use std::time::{Duration, Instant};
+
+///
+/// 按键通道
+///
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+pub enum Channel{
+    Channel0, // 单键或左键, 对应 `status`/`channel_0`
+    Channel1, // 右键, 对应 `channel_1`
+    Both, // 左右键同时, 对应 `dual_channel`
+}
+
+impl Channel{
+    fn index(self)->usize{
+        match self {
+            Channel::Channel0 => 0,
+            Channel::Channel1 => 1,
+            Channel::Both => 2,
+        }
+    }
+}
+
+///
+/// 按键手势
+///
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum Gesture{
+    Click, // 单击
+    DoubleClick, // 双击
+    LongClick, // 长按( 86 开关不区分按下和松开 )
+    Press, // 长按开始
+    Release{held:Option<Duration>}, // 长按松开, 附带按住的时长
+    Shake, // 摇一摇( `sensor_switch.aq3` )
+}
+
+///
+/// 按键事件
+///
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub struct ButtonEvent{
+    pub channel:Channel,
+    pub gesture:Gesture,
+}
+
+///
+/// 无线按键和无线墙壁开关( `switch`/`sensor_switch.aq2`/`sensor_switch.aq3`/`86sw1`/`86sw2` )
+///
+/// 无线按键通过 `status` 上报, 86 开关按通道上报 `channel_0`/`channel_1`, 双键同时按下上报 `dual_channel`;
+/// `both_click`/`long_both_click` 会统一转换成 `Channel::Both` 通道的单击和长按
+///
+/// ```
+/// use aqara_rs::protocol::Message;
+/// use aqara_rs::devices::{Device, Button, ButtonEvent, Channel, Gesture};
+/// let mut button = Button::new("158d0001a2b3c4".parse().unwrap());
+/// if let Message::Report(frame) = Message::parse(br#"{"cmd":"report","model":"86sw2","sid":"158d0001a2b3c4","data":"{\"dual_channel\":\"both_click\"}"}"#).unwrap() {
+///     assert_eq!(button.update(&frame),vec![ButtonEvent{channel:Channel::Both,gesture:Gesture::Click}]);
+/// }
+/// ```
+///
+#[derive(Debug,Clone)]
+pub struct Button{
+    sid:Sid,
+    pressed:[Option<Instant>;3],
+}
+
+impl Button{
+    pub fn new(sid:Sid)->Self{
+        Self{sid,pressed:[None;3]}
+    }
+
+    ///
+    /// 通道是否处于长按状态
+    ///
+    pub fn is_pressed(&self,channel:Channel)->bool{
+        self.pressed[channel.index()].is_some()
+    }
+
+    fn gesture(&mut self,channel:Channel,status:DeviceStatus,at:Instant)->Option<ButtonEvent>{
+        let (channel,gesture) = match status {
+            DeviceStatus::Click => (channel,Gesture::Click),
+            DeviceStatus::DoubleClick => (channel,Gesture::DoubleClick),
+            DeviceStatus::LongClick => (channel,Gesture::LongClick),
+            DeviceStatus::BothClick => (Channel::Both,Gesture::Click),
+            DeviceStatus::LongBothClick => (Channel::Both,Gesture::LongClick),
+            DeviceStatus::Shake => (channel,Gesture::Shake),
+            DeviceStatus::LongClickPress => {
+                self.pressed[channel.index()] = Some(at);
+                (channel,Gesture::Press)
+            },
+            DeviceStatus::LongClickRelease => {
+                let held = self.pressed[channel.index()].take().map(|pressed| at.saturating_duration_since(pressed));
+                (channel,Gesture::Release{held})
+            },
+            _ => return None,
+        };
+        Some(ButtonEvent{channel,gesture})
+    }
+}
+
+impl Device for Button{
+    type Event = ButtonEvent;
+
+    fn supports(model:&Model)->bool{
+        let model = model.as_str();
+        model == "switch" || model.starts_with("sensor_switch") || model.starts_with("86sw") || model.starts_with("sensor_86sw")
+    }
+
+    fn sid(&self)->&Sid{
+        &self.sid
+    }
+
+    fn update_at(&mut self,frame:&Frame,at:Instant)->Vec<ButtonEvent>{
+        let mut events = Vec::new();
+        if !self.accepts(frame) {
+            return events;
+        }
+        let keys = [
+            ("status",Channel::Channel0),
+            ("channel_0",Channel::Channel0),
+            ("channel_1",Channel::Channel1),
+            ("dual_channel",Channel::Both),
+        ];
+        for (key,channel) in keys.iter() {
+            if let Some(status) = frame.data.device_status(key) {
+                events.extend(self.gesture(*channel,status,at));
+            }
+        }
+        events
+    }
+}
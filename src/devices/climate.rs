@@ -0,0 +1,87 @@
+use crate::protocol::{Frame, Message};
+use crate::types::Model;
+
+///
+/// 温度的有效范围( ℃ ), 超出范围的一般是传感器异常时的哨兵值
+///
+const TEMPERATURE_RANGE:std::ops::RangeInclusive<f32> = -40.0..=85.0;
+
+///
+/// 湿度的有效范围( %RH ), 传感器异常时会上报 `10000`
+///
+const HUMIDITY_RANGE:std::ops::Range<f32> = 0.0..100.0;
+
+///
+/// 气压的有效范围( kPa )
+///
+const PRESSURE_RANGE:std::ops::RangeInclusive<f32> = 30.0..=110.0;
+
+
+///
+/// 温湿度读数
+///
+/// 报文中温湿度为百分之一单位的字符串( `"2350"` => 23.50 ℃ ), 气压单位为 Pa,
+/// 这里统一换算为 ℃, %RH 和 kPa; 超出有效范围的读数会被丢弃
+///
+/// ```
+/// use aqara_rs::protocol::Message;
+/// use aqara_rs::devices::Climate;
+/// let msg = Message::parse(br#"{"cmd":"report","model":"weather.v1","sid":"158d0001a2b3c4","data":"{\"temperature\":\"2350\",\"humidity\":\"10000\",\"pressure\":\"100620\"}"}"#).unwrap();
+/// let climate = Climate::from_message(&msg).unwrap();
+/// assert_eq!(climate.temperature,Some(23.5));
+/// assert_eq!(climate.humidity,None);
+/// assert_eq!(climate.pressure,Some(100.62));
+/// ```
+///
+#[derive(Debug,Clone,PartialEq)]
+pub struct Climate{
+    pub model:Model,
+    pub temperature:Option<f32>, // ℃
+    pub humidity:Option<f32>, // %RH
+    pub pressure:Option<f32>, // kPa
+}
+
+impl Climate{
+
+    ///
+    /// 是否为温湿度类设备
+    ///
+    pub fn supports(model:&Model)->bool{
+        model.as_str().starts_with("sensor_ht") || model.as_str().starts_with("weather")
+    }
+
+    ///
+    /// 从 `report`/`read_ack`/`heartbeat` 报文获取读数
+    ///
+    pub fn from_message(message:&Message)->Option<Self>{
+        match message {
+            Message::Report(frame)
+            | Message::ReadAck(frame)
+            | Message::Heartbeat(frame) => Self::from_frame(frame),
+            _ => None,
+        }
+    }
+
+    ///
+    /// 从设备报文获取读数, 型号不符或者没有任何有效读数时返回 `None`
+    ///
+    pub fn from_frame(frame:&Frame)->Option<Self>{
+        let model = frame.model.as_ref().filter(|model| Self::supports(model))?;
+        let climate = Self{
+            model: model.clone(),
+            temperature: frame.data.temperature()
+                .map(|value| value as f32 / 100.0)
+                .filter(|value| TEMPERATURE_RANGE.contains(value)),
+            humidity: frame.data.humidity()
+                .map(|value| value as f32 / 100.0)
+                .filter(|value| HUMIDITY_RANGE.contains(value)),
+            pressure: frame.data.pressure()
+                .map(|value| value as f32 / 1000.0)
+                .filter(|value| PRESSURE_RANGE.contains(value)),
+        };
+        if climate.temperature.is_none() && climate.humidity.is_none() && climate.pressure.is_none() {
+            return None;
+        }
+        Some(climate)
+    }
+}
@@ -0,0 +1,108 @@
+use crate::devices::Device;
+use crate::prelude::DeviceStatus;
+use crate::protocol::Frame;
+use crate::types::{Model, Sid};
+use std::time::{Duration, Instant};
+
+///
+/// 魔方事件
+///
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum CubeEvent{
+    Flip90, // 翻转 90 度
+    Flip180, // 翻转 180 度
+    Move, // 平移
+    TapTwice, // 双击桌面
+    ShakeAir, // 空中摇动
+    Swing, // 空中摆动
+    Alert, // 唤醒
+    FreeFall, // 自由落体
+    Rotate{degrees:i32,duration:Option<Duration>}, // 旋转角度, 正数为顺时针; 新固件会附带旋转用时
+}
+
+///
+/// 魔方控制器( `cube`/`sensor_cube.aqgl01` )
+///
+/// 动作通过 `status` 上报, 旋转通过 `rotate` 上报带符号的角度, 新固件的格式为 `角度,毫秒`
+///
+/// ```
+/// use std::time::Duration;
+/// use aqara_rs::protocol::Message;
+/// use aqara_rs::devices::{Device, Cube, CubeEvent};
+/// let mut cube = Cube::new("158d0001a2b3c4".parse().unwrap());
+/// if let Message::Report(frame) = Message::parse(br#"{"cmd":"report","model":"cube","sid":"158d0001a2b3c4","data":"{\"rotate\":\"-32,500\"}"}"#).unwrap() {
+///     assert_eq!(cube.update(&frame),vec![CubeEvent::Rotate{degrees:-32,duration:Some(Duration::from_millis(500))}]);
+/// }
+/// ```
+///
+#[derive(Debug,Clone)]
+pub struct Cube{
+    sid:Sid,
+    last_event:Option<CubeEvent>,
+}
+
+impl Cube{
+    pub fn new(sid:Sid)->Self{
+        Self{sid,last_event:None}
+    }
+
+    ///
+    /// 最近一次的动作
+    ///
+    pub fn last_event(&self)->Option<CubeEvent>{
+        self.last_event
+    }
+
+    fn gesture(status:DeviceStatus)->Option<CubeEvent>{
+        match status {
+            DeviceStatus::Flip90 => Some(CubeEvent::Flip90),
+            DeviceStatus::Flip180 => Some(CubeEvent::Flip180),
+            DeviceStatus::Move => Some(CubeEvent::Move),
+            DeviceStatus::TapTwice => Some(CubeEvent::TapTwice),
+            DeviceStatus::ShakeAir => Some(CubeEvent::ShakeAir),
+            DeviceStatus::Swing => Some(CubeEvent::Swing),
+            DeviceStatus::Alert => Some(CubeEvent::Alert),
+            DeviceStatus::FreeFall => Some(CubeEvent::FreeFall),
+            _ => None,
+        }
+    }
+
+    fn rotation(frame:&Frame)->Option<CubeEvent>{
+        if let Some(degrees) = frame.data.get_i64("rotate") {
+            return Some(CubeEvent::Rotate{degrees:degrees as i32,duration:None});
+        }
+        let mut parts = frame.data.get_str("rotate")?.split(',');
+        let degrees = parts.next()?.trim().parse().ok()?;
+        let duration = match parts.next() {
+            Some(millis) => Some(Duration::from_millis(millis.trim().parse().ok()?)),
+            None => None,
+        };
+        Some(CubeEvent::Rotate{degrees,duration})
+    }
+}
+
+impl Device for Cube{
+    type Event = CubeEvent;
+
+    fn supports(model:&Model)->bool{
+        let model = model.as_str();
+        model == "cube" || model.starts_with("sensor_cube")
+    }
+
+    fn sid(&self)->&Sid{
+        &self.sid
+    }
+
+    fn update_at(&mut self,frame:&Frame,_at:Instant)->Vec<CubeEvent>{
+        let mut events = Vec::new();
+        if !self.accepts(frame) {
+            return events;
+        }
+        events.extend(frame.data.device_status("status").and_then(Self::gesture));
+        events.extend(Self::rotation(frame));
+        if let Some(event) = events.last() {
+            self.last_event = Some(*event);
+        }
+        events
+    }
+}
@@ -0,0 +1,157 @@
+use crate::devices::Device;
+use crate::prelude::DeviceStatus;
+use crate::protocol::{Frame, WriteCommand};
+use crate::types::{Model, Sid};
+use std::time::Instant;
+
+///
+/// 窗帘事件
+///
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub enum CurtainEvent{
+    Opening, // 正在打开
+    Closing, // 正在关闭
+    Stopped, // 停止
+    Auto, // 自动模式
+    Position{percent:u8}, // 当前开合位置, 0 为完全关闭, 100 为完全打开
+}
+
+///
+/// 窗帘电机( `curtain` )
+///
+/// 通过 `curtain_status` 写入 `open`/`close`/`stop`/`auto`, 通过 `curtain_level` 写入 0~100 的目标位置,
+/// 电机会上报当前的 `curtain_level`
+///
+/// ```
+/// use aqara_rs::protocol::Message;
+/// use aqara_rs::devices::{Device, Curtain, CurtainEvent};
+/// let mut curtain = Curtain::new("158d0001a2b3c4".parse().unwrap(),"curtain".parse().unwrap());
+/// let command = curtain.set_position(60);
+/// assert_eq!(command.data().get_str("curtain_level"),Some("60"));
+/// if let Message::Report(frame) = Message::parse(br#"{"cmd":"report","model":"curtain","sid":"158d0001a2b3c4","data":"{\"curtain_level\":\"60\"}"}"#).unwrap() {
+///     assert_eq!(curtain.update(&frame),vec![CurtainEvent::Position{percent:60}]);
+/// }
+/// assert_eq!(curtain.position(),Some(60));
+/// assert_eq!(curtain.target(),None);
+/// ```
+///
+#[derive(Debug,Clone)]
+pub struct Curtain{
+    sid:Sid,
+    model:Model,
+    status:Option<DeviceStatus>,
+    position:Option<u8>,
+    target:Option<u8>,
+}
+
+impl Curtain{
+    pub fn new(sid:Sid,model:Model)->Self{
+        Self{sid,model,status:None,position:None,target:None}
+    }
+
+    ///
+    /// 最近一次上报的运行状态
+    ///
+    pub fn status(&self)->Option<&DeviceStatus>{
+        self.status.as_ref()
+    }
+
+    ///
+    /// 最近一次上报的位置
+    ///
+    pub fn position(&self)->Option<u8>{
+        self.position
+    }
+
+    ///
+    /// 已经下发但还没有到达的目标位置
+    ///
+    pub fn target(&self)->Option<u8>{
+        self.target
+    }
+
+    ///
+    /// 生成打开命令
+    ///
+    pub fn open(&mut self)->WriteCommand{
+        self.target = Some(100);
+        self.command("curtain_status",DeviceStatus::Open.as_str())
+    }
+
+    ///
+    /// 生成关闭命令
+    ///
+    pub fn close(&mut self)->WriteCommand{
+        self.target = Some(0);
+        self.command("curtain_status",DeviceStatus::Close.as_str())
+    }
+
+    ///
+    /// 生成停止命令
+    ///
+    pub fn stop(&mut self)->WriteCommand{
+        self.target = None;
+        self.command("curtain_status",DeviceStatus::Stop.as_str())
+    }
+
+    ///
+    /// 生成移动到指定位置的命令, 超过 100 按照 100 处理
+    ///
+    pub fn set_position(&mut self,percent:u8)->WriteCommand{
+        let percent = percent.min(100);
+        self.target = Some(percent);
+        self.command("curtain_level",percent.to_string())
+    }
+
+    fn command<T:Into<json::JsonValue>>(&self,key:&str,value:T)->WriteCommand{
+        WriteCommand::new(self.sid.clone(),self.model.clone())
+            .set(key,value)
+            .retry(true)
+    }
+}
+
+impl Device for Curtain{
+    type Event = CurtainEvent;
+
+    fn supports(model:&Model)->bool{
+        model.as_str().starts_with("curtain")
+    }
+
+    fn sid(&self)->&Sid{
+        &self.sid
+    }
+
+    fn update_at(&mut self,frame:&Frame,_at:Instant)->Vec<CurtainEvent>{
+        let mut events = Vec::new();
+        if !self.accepts(frame) {
+            return events;
+        }
+        if let Some(status) = frame.data.device_status("curtain_status") {
+            let event = match status {
+                DeviceStatus::Open => Some(CurtainEvent::Opening),
+                DeviceStatus::Close => Some(CurtainEvent::Closing),
+                DeviceStatus::Stop => Some(CurtainEvent::Stopped),
+                DeviceStatus::Auto => Some(CurtainEvent::Auto),
+                _ => None,
+            };
+            if let Some(event) = event {
+                if event == CurtainEvent::Stopped {
+                    self.target = None;
+                }
+                self.status = Some(status);
+                events.push(event);
+            }
+        }
+        if let Some(percent) = frame.data.get_i64("curtain_level").filter(|level| (0..=100).contains(level)) {
+            let percent = percent as u8;
+            if self.target == Some(percent) {
+                self.target = None;
+            }
+            if self.position != Some(percent) {
+                self.position = Some(percent);
+                events.push(CurtainEvent::Position{percent});
+            }
+        }
+        events
+    }
+}
@@ -0,0 +1,101 @@
+use crate::devices::Device;
+use crate::prelude::DeviceStatus;
+use crate::protocol::Frame;
+use crate::types::{Model, Sid};
+use std::time::Instant;
+
+///
+/// 门窗传感器事件
+///
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub enum DoorEvent{
+    Opened, // 打开
+    Closed, // 关闭
+    LeftOpen{seconds:u32}, // 打开之后持续未关闭的秒数( 60/120/300... )
+}
+
+///
+/// 门窗传感器( `magnet`/`sensor_magnet.aq2` )
+///
+/// 上报 `status` 为 `open`/`close`, 打开之后长时间未关闭会上报 `no_close` 及已经打开的秒数
+///
+/// ```
+/// use aqara_rs::protocol::Message;
+/// use aqara_rs::devices::{Device, Door, DoorEvent};
+/// let mut door = Door::new("158d0001a2b3c4".parse().unwrap());
+/// if let Message::Report(frame) = Message::parse(br#"{"cmd":"report","model":"magnet","sid":"158d0001a2b3c4","data":"{\"no_close\":\"60\"}"}"#).unwrap() {
+///     assert_eq!(door.update(&frame),vec![DoorEvent::LeftOpen{seconds:60}]);
+/// }
+/// assert_eq!(door.is_open(),Some(true));
+/// ```
+///
+#[derive(Debug,Clone)]
+pub struct Door{
+    sid:Sid,
+    open:Option<bool>,
+    changed_at:Option<Instant>,
+}
+
+impl Door{
+    pub fn new(sid:Sid)->Self{
+        Self{sid,open:None,changed_at:None}
+    }
+
+    ///
+    /// 当前是否打开, 未收到过状态时为 `None`
+    ///
+    pub fn is_open(&self)->Option<bool>{
+        self.open
+    }
+
+    ///
+    /// 最近一次开关状态变化的时间
+    ///
+    pub fn changed_at(&self)->Option<Instant>{
+        self.changed_at
+    }
+
+    fn change(&mut self,open:bool,at:Instant)->bool{
+        if self.open == Some(open) {
+            return false;
+        }
+        self.open = Some(open);
+        self.changed_at = Some(at);
+        true
+    }
+}
+
+impl Device for Door{
+    type Event = DoorEvent;
+
+    fn supports(model:&Model)->bool{
+        model.as_str().starts_with("magnet") || model.as_str().starts_with("sensor_magnet")
+    }
+
+    fn sid(&self)->&Sid{
+        &self.sid
+    }
+
+    fn update_at(&mut self,frame:&Frame,at:Instant)->Vec<DoorEvent>{
+        let mut events = Vec::new();
+        if !self.accepts(frame) {
+            return events;
+        }
+        // 2.x 协议使用 window_status
+        let status = frame.data.device_status("status").or_else(|| frame.data.device_status("window_status"));
+        match status {
+            Some(DeviceStatus::Open) if self.change(true,at) => events.push(DoorEvent::Opened),
+            Some(DeviceStatus::Close) if self.change(false,at) => events.push(DoorEvent::Closed),
+            _ => {},
+        }
+        if let Some(seconds) = frame.data.get_i64("no_close") {
+            // 上报 no_close 时门一定是打开的, 开门时间按照已经打开的秒数倒推
+            if self.open != Some(true) {
+                self.open = Some(true);
+                self.changed_at = at.checked_sub(std::time::Duration::from_secs(seconds.max(0) as u64));
+            }
+            events.push(DoorEvent::LeftOpen{seconds: seconds.max(0) as u32});
+        }
+        events
+    }
+}
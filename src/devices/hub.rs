@@ -0,0 +1,433 @@
+use crate::devices::Device;
+use crate::protocol::{Frame, WriteCommand};
+use crate::types::{Model, Sid};
+use std::time::Instant;
+
+///
+/// 网关夜灯颜色和亮度
+///
+/// 报文中的 `rgb` 是一个 32 位整数, 最高字节为亮度( 0~100 ), 低 24 位依次为红绿蓝
+///
+/// ```
+/// use aqara_rs::devices::Light;
+/// let light = Light::from_rgb(0x64ff0000);
+/// assert_eq!(light,Light{brightness:100,red:255,green:0,blue:0});
+/// assert_eq!(light.to_rgb(),0x64ff0000);
+/// ```
+///
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash,Default)]
+pub struct Light{
+    pub brightness:u8, // 亮度, 0 为关闭
+    pub red:u8,
+    pub green:u8,
+    pub blue:u8,
+}
+
+impl Light{
+    pub fn new(brightness:u8,red:u8,green:u8,blue:u8)->Self{
+        Self{brightness:brightness.min(100),red,green,blue}
+    }
+
+    ///
+    /// 解析网关上报的 `rgb`
+    ///
+    pub fn from_rgb(rgb:u32)->Self{
+        Self::new((rgb >> 24) as u8,(rgb >> 16) as u8,(rgb >> 8) as u8,rgb as u8)
+    }
+
+    ///
+    /// 打包成写入网关的 `rgb`
+    ///
+    pub fn to_rgb(&self)->u32{
+        (self.brightness.min(100) as u32) << 24
+            | (self.red as u32) << 16
+            | (self.green as u32) << 8
+            | self.blue as u32
+    }
+
+    ///
+    /// 是否点亮
+    ///
+    pub fn is_on(&self)->bool{
+        self.brightness > 0
+    }
+}
+
+///
+/// 停止播放的 `mid`
+///
+pub const STOP_TONE_MID:u32 = 10000;
+
+///
+/// 用户上传的铃声编号, 只能是 10001 以上, 不会和内置铃声或者停止播放混淆
+///
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+pub struct UserTone(u32);
+
+impl UserTone{
+    pub fn new(mid:u32)->Option<Self>{
+        if mid > STOP_TONE_MID { Some(UserTone(mid)) } else { None }
+    }
+
+    pub fn mid(&self)->u32{
+        self.0
+    }
+}
+
+///
+/// 网关铃声
+///
+/// 内置铃声的 `mid` 为 0~29, 用户上传的铃声从 10001 开始编号, 写入 10000 表示停止播放
+///
+/// ```
+/// use aqara_rs::devices::Tone;
+/// assert_eq!("doorbell".parse::<Tone>(),Ok(Tone::Doorbell));
+/// assert_eq!(Tone::Doorbell.mid(),10);
+/// assert_eq!(Tone::from_mid(10001).map(|tone| tone.mid()),Some(10001));
+/// assert_eq!(Tone::from_mid(10000),None);
+/// assert_eq!("user(10001)".parse::<Tone>().map(|tone| tone.to_string()),Ok("user(10001)".to_string()));
+/// assert_eq!(Tone::from_mid(9),None);
+/// ```
+///
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+pub enum Tone{
+    PoliceCar1, // 0: 警车音 1
+    PoliceCar2, // 1: 警车音 2
+    Accident, // 2: 安全事故音
+    Countdown, // 3: 导弹倒计时
+    Ghost, // 4: 鬼叫声
+    SniperRifle, // 5: 狙击枪
+    Battle, // 6: 激战声
+    AirRaid, // 7: 空袭警报
+    Bark, // 8: 狗叫声
+    Doorbell, // 10: 门铃声
+    KnockAtDoor, // 11: 敲门声
+    Amuse, // 12: 搞怪声
+    AlarmClock, // 13: 报警时钟
+    MiMix, // 20: MiMix
+    Enthusiastic, // 21: 热情
+    GuitarClassic, // 22: 古典吉他
+    IceWorldPiano, // 23: 冰雪钢琴
+    LeisureTime, // 24: 悠闲时光
+    Childhood, // 25: 童年
+    MorningStreamlet, // 26: 晨曦小溪
+    MusicBox, // 27: 八音盒
+    Orange, // 28: 橘子
+    Thinker, // 29: 思考者
+    User(UserTone), // 10001 以上: 用户铃声
+}
+
+impl Tone{
+    ///
+    /// 全部内置铃声
+    ///
+    pub const BUILTIN:[Tone;23] = [
+        Tone::PoliceCar1,
+        Tone::PoliceCar2,
+        Tone::Accident,
+        Tone::Countdown,
+        Tone::Ghost,
+        Tone::SniperRifle,
+        Tone::Battle,
+        Tone::AirRaid,
+        Tone::Bark,
+        Tone::Doorbell,
+        Tone::KnockAtDoor,
+        Tone::Amuse,
+        Tone::AlarmClock,
+        Tone::MiMix,
+        Tone::Enthusiastic,
+        Tone::GuitarClassic,
+        Tone::IceWorldPiano,
+        Tone::LeisureTime,
+        Tone::Childhood,
+        Tone::MorningStreamlet,
+        Tone::MusicBox,
+        Tone::Orange,
+        Tone::Thinker,
+    ];
+
+    pub fn mid(&self)->u32{
+        match self {
+            Tone::PoliceCar1 => 0,
+            Tone::PoliceCar2 => 1,
+            Tone::Accident => 2,
+            Tone::Countdown => 3,
+            Tone::Ghost => 4,
+            Tone::SniperRifle => 5,
+            Tone::Battle => 6,
+            Tone::AirRaid => 7,
+            Tone::Bark => 8,
+            Tone::Doorbell => 10,
+            Tone::KnockAtDoor => 11,
+            Tone::Amuse => 12,
+            Tone::AlarmClock => 13,
+            Tone::MiMix => 20,
+            Tone::Enthusiastic => 21,
+            Tone::GuitarClassic => 22,
+            Tone::IceWorldPiano => 23,
+            Tone::LeisureTime => 24,
+            Tone::Childhood => 25,
+            Tone::MorningStreamlet => 26,
+            Tone::MusicBox => 27,
+            Tone::Orange => 28,
+            Tone::Thinker => 29,
+            Tone::User(tone) => tone.mid(),
+        }
+    }
+
+    ///
+    /// 根据 `mid` 查找铃声, 未定义的编号和停止播放的 10000 返回 `None`
+    ///
+    pub fn from_mid(mid:u32)->Option<Self>{
+        match mid {
+            0 => Some(Tone::PoliceCar1),
+            1 => Some(Tone::PoliceCar2),
+            2 => Some(Tone::Accident),
+            3 => Some(Tone::Countdown),
+            4 => Some(Tone::Ghost),
+            5 => Some(Tone::SniperRifle),
+            6 => Some(Tone::Battle),
+            7 => Some(Tone::AirRaid),
+            8 => Some(Tone::Bark),
+            10 => Some(Tone::Doorbell),
+            11 => Some(Tone::KnockAtDoor),
+            12 => Some(Tone::Amuse),
+            13 => Some(Tone::AlarmClock),
+            20 => Some(Tone::MiMix),
+            21 => Some(Tone::Enthusiastic),
+            22 => Some(Tone::GuitarClassic),
+            23 => Some(Tone::IceWorldPiano),
+            24 => Some(Tone::LeisureTime),
+            25 => Some(Tone::Childhood),
+            26 => Some(Tone::MorningStreamlet),
+            27 => Some(Tone::MusicBox),
+            28 => Some(Tone::Orange),
+            29 => Some(Tone::Thinker),
+            mid => UserTone::new(mid).map(Tone::User),
+        }
+    }
+
+    ///
+    /// 内置铃声的名称, 用户铃声为 `user`
+    ///
+    pub fn name(&self)->&'static str{
+        match self {
+            Tone::PoliceCar1 => "police_car_1",
+            Tone::PoliceCar2 => "police_car_2",
+            Tone::Accident => "accident",
+            Tone::Countdown => "countdown",
+            Tone::Ghost => "ghost",
+            Tone::SniperRifle => "sniper_rifle",
+            Tone::Battle => "battle",
+            Tone::AirRaid => "air_raid",
+            Tone::Bark => "bark",
+            Tone::Doorbell => "doorbell",
+            Tone::KnockAtDoor => "knock_at_door",
+            Tone::Amuse => "amuse",
+            Tone::AlarmClock => "alarm_clock",
+            Tone::MiMix => "mimix",
+            Tone::Enthusiastic => "enthusiastic",
+            Tone::GuitarClassic => "guitar_classic",
+            Tone::IceWorldPiano => "ice_world_piano",
+            Tone::LeisureTime => "leisure_time",
+            Tone::Childhood => "childhood",
+            Tone::MorningStreamlet => "morning_streamlet",
+            Tone::MusicBox => "music_box",
+            Tone::Orange => "orange",
+            Tone::Thinker => "thinker",
+            Tone::User(_) => "user",
+        }
+    }
+}
+
+impl std::str::FromStr for Tone{
+    type Err = ParseToneError;
+
+    fn from_str(s:&str)->Result<Self,Self::Err>{
+        // 用户铃声按照 `Display` 的格式 `user(<mid>)` 解析
+        if let Some(mid) = s.strip_prefix("user(").and_then(|rest| rest.strip_suffix(')')) {
+            return mid.trim().parse().ok()
+                .and_then(UserTone::new)
+                .map(Tone::User)
+                .ok_or_else(|| ParseToneError(s.to_string()));
+        }
+        Tone::BUILTIN.iter()
+            .find(|tone| tone.name() == s)
+            .copied()
+            .ok_or_else(|| ParseToneError(s.to_string()))
+    }
+}
+
+impl std::fmt::Display for Tone{
+    fn fmt(&self,f:&mut std::fmt::Formatter<'_>)->std::fmt::Result{
+        match self {
+            Tone::User(tone) => write!(f,"user({})",tone.mid()),
+            tone => f.write_str(tone.name()),
+        }
+    }
+}
+
+///
+/// 未知的铃声名称
+///
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct ParseToneError(pub String);
+
+impl std::fmt::Display for ParseToneError{
+    fn fmt(&self,f:&mut std::fmt::Formatter<'_>)->std::fmt::Result{
+        write!(f,"unknown tone `{}`",self.0)
+    }
+}
+
+impl std::error::Error for ParseToneError{}
+
+
+///
+/// 网关事件
+///
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub enum HubEvent{
+    Light(Light), // 夜灯颜色或亮度变化
+    Illumination{value:u32}, // 网关光照传感器读数
+}
+
+///
+/// 网关自身( `gateway`/`gateway.v3` 等 ), 可以作为状态灯和提示音使用
+///
+/// 网关在心跳和上报中带有夜灯 `rgb` 和光照值 `illumination`; 夜灯, 铃声( `mid`/`vol` )和子设备配对都通过签名之后写入网关 sid 控制
+///
+/// ```
+/// use aqara_rs::protocol::Message;
+/// use aqara_rs::devices::{Device, Hub, HubEvent, Light};
+/// let mut hub = Hub::new("34ce00112233".parse().unwrap(),"gateway".parse().unwrap());
+/// if let Message::Report(frame) = Message::parse(br#"{"cmd":"report","model":"gateway","sid":"34ce00112233","data":"{\"rgb\":1694433535,\"illumination\":\"495\"}"}"#).unwrap() {
+///     assert_eq!(hub.update(&frame),vec![HubEvent::Light(Light::new(100,255,0,255)),HubEvent::Illumination{value:495}]);
+/// }
+/// let command = hub.set_brightness(50);
+/// assert_eq!(command.data().get_i64("rgb"),Some(0x32ff00ff));
+/// ```
+///
+#[derive(Debug,Clone)]
+pub struct Hub{
+    sid:Sid,
+    model:Model,
+    light:Option<Light>,
+    illumination:Option<u32>,
+}
+
+impl Hub{
+    pub fn new(sid:Sid,model:Model)->Self{
+        Self{sid,model,light:None,illumination:None}
+    }
+
+    ///
+    /// 最近一次上报的夜灯状态
+    ///
+    pub fn light(&self)->Option<Light>{
+        self.light
+    }
+
+    ///
+    /// 最近一次上报的光照值
+    ///
+    pub fn illumination(&self)->Option<u32>{
+        self.illumination
+    }
+
+    ///
+    /// 生成设置夜灯的命令
+    ///
+    pub fn set_light(&self,light:Light)->WriteCommand{
+        self.command().set("rgb",light.to_rgb())
+    }
+
+    ///
+    /// 生成设置颜色的命令, 保持当前亮度, 夜灯关闭或者状态未知时使用最大亮度
+    ///
+    pub fn set_color(&self,red:u8,green:u8,blue:u8)->WriteCommand{
+        let brightness = self.light.filter(Light::is_on).map(|light| light.brightness).unwrap_or(100);
+        self.set_light(Light::new(brightness,red,green,blue))
+    }
+
+    ///
+    /// 生成设置亮度的命令, 保持当前颜色, 状态未知时使用白色
+    ///
+    pub fn set_brightness(&self,brightness:u8)->WriteCommand{
+        let light = self.light.unwrap_or(Light::new(0,255,255,255));
+        self.set_light(Light{brightness:brightness.min(100),..light})
+    }
+
+    ///
+    /// 生成关闭夜灯的命令
+    ///
+    pub fn turn_off_light(&self)->WriteCommand{
+        self.set_light(Light::default())
+    }
+
+    ///
+    /// 生成播放铃声的命令, 音量为 0~100
+    ///
+    pub fn play(&self,tone:Tone,volume:u8)->WriteCommand{
+        self.command()
+            .set("mid",tone.mid())
+            .set("vol",volume.min(100))
+    }
+
+    ///
+    /// 生成停止播放的命令
+    ///
+    pub fn stop_sound(&self)->WriteCommand{
+        self.command().set("mid",STOP_TONE_MID)
+    }
+
+    ///
+    /// 生成允许子设备入网的命令, 网关收到之后会打开一段时间的配对窗口
+    ///
+    pub fn permit_join(&self)->WriteCommand{
+        self.command().set("join_permission","yes")
+    }
+
+    ///
+    /// 生成从网关移除子设备的命令
+    ///
+    pub fn remove_device(&self,sid:&Sid)->WriteCommand{
+        self.command().set("remove_device",sid.as_str())
+    }
+
+    fn command(&self)->WriteCommand{
+        WriteCommand::new(self.sid.clone(),self.model.clone()).retry(true)
+    }
+}
+
+impl Device for Hub{
+    type Event = HubEvent;
+
+    fn supports(model:&Model)->bool{
+        model.is_gateway()
+    }
+
+    fn sid(&self)->&Sid{
+        &self.sid
+    }
+
+    fn update_at(&mut self,frame:&Frame,_at:Instant)->Vec<HubEvent>{
+        let mut events = Vec::new();
+        if !self.accepts(frame) {
+            return events;
+        }
+        if let Some(light) = frame.data.get_i64("rgb").map(|rgb| Light::from_rgb(rgb as u32)) {
+            if self.light != Some(light) {
+                self.light = Some(light);
+                events.push(HubEvent::Light(light));
+            }
+        }
+        if let Some(value) = frame.data.get_i64("illumination").map(|value| value as u32) {
+            if self.illumination != Some(value) {
+                self.illumination = Some(value);
+                events.push(HubEvent::Illumination{value});
+            }
+        }
+        events
+    }
+}
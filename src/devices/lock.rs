@@ -0,0 +1,138 @@
+use crate::devices::Device;
+use crate::protocol::Frame;
+use crate::types::{Model, Sid};
+use std::convert::TryFrom;
+use std::time::Instant;
+
+///
+/// 开锁方式
+///
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+pub enum UnlockMethod{
+    Fingerprint, // `fing_verified`
+    Password, // `psw_verified`
+    Card, // `card_verified`
+}
+
+impl UnlockMethod{
+    fn key(&self)->&'static str{
+        match self {
+            UnlockMethod::Fingerprint => "fing_verified",
+            UnlockMethod::Password => "psw_verified",
+            UnlockMethod::Card => "card_verified",
+        }
+    }
+}
+
+///
+/// 门锁事件
+///
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub enum LockEvent{
+    Unlocked{method:UnlockMethod,user:u32}, // 验证通过, 附带开锁方式和用户 id
+    Rejected{attempts:u32}, // 验证失败, 附带门锁上报的连续失败次数
+}
+
+///
+/// 智能门锁( `lock.aq1` )
+///
+/// 验证通过时按照开锁方式上报 `fing_verified`/`psw_verified`/`card_verified`, 值为用户 id;
+/// 验证失败时上报 `verified_wrong`, 值为连续失败的次数. 成功开锁之后失败次数清零
+///
+/// ```
+/// use aqara_rs::protocol::Message;
+/// use aqara_rs::devices::{Device, Lock, LockEvent, UnlockMethod};
+/// let mut lock = Lock::new("158d0001a2b3c4".parse().unwrap());
+/// if let Message::Report(frame) = Message::parse(br#"{"cmd":"report","model":"lock.aq1","sid":"158d0001a2b3c4","data":"{\"fing_verified\":\"65536\"}"}"#).unwrap() {
+///     assert_eq!(lock.update(&frame),vec![LockEvent::Unlocked{method:UnlockMethod::Fingerprint,user:65536}]);
+/// }
+/// assert_eq!(lock.last_user(),Some(65536));
+/// ```
+///
+#[derive(Debug,Clone)]
+pub struct Lock{
+    sid:Sid,
+    last_unlock:Option<(UnlockMethod,u32,Instant)>,
+    failed_attempts:u32,
+    total_failed:u32,
+}
+
+impl Lock{
+    pub fn new(sid:Sid)->Self{
+        Self{sid,last_unlock:None,failed_attempts:0,total_failed:0}
+    }
+
+    ///
+    /// 最近一次开锁的方式
+    ///
+    pub fn last_method(&self)->Option<UnlockMethod>{
+        self.last_unlock.map(|(method,_,_)| method)
+    }
+
+    ///
+    /// 最近一次开锁的用户 id
+    ///
+    pub fn last_user(&self)->Option<u32>{
+        self.last_unlock.map(|(_,user,_)| user)
+    }
+
+    ///
+    /// 最近一次开锁的时间
+    ///
+    pub fn last_unlocked_at(&self)->Option<Instant>{
+        self.last_unlock.map(|(_,_,at)| at)
+    }
+
+    ///
+    /// 上次开锁之后连续失败的次数
+    ///
+    pub fn failed_attempts(&self)->u32{
+        self.failed_attempts
+    }
+
+    ///
+    /// 累计失败的次数
+    ///
+    pub fn total_failed(&self)->u32{
+        self.total_failed
+    }
+}
+
+impl Device for Lock{
+    type Event = LockEvent;
+
+    fn supports(model:&Model)->bool{
+        model.as_str().starts_with("lock")
+    }
+
+    fn sid(&self)->&Sid{
+        &self.sid
+    }
+
+    fn update_at(&mut self,frame:&Frame,at:Instant)->Vec<LockEvent>{
+        let mut events = Vec::new();
+        if !self.accepts(frame) {
+            return events;
+        }
+        let methods = [UnlockMethod::Fingerprint,UnlockMethod::Password,UnlockMethod::Card];
+        for method in methods.iter() {
+            if let Some(user) = frame.data.get_i64(method.key()).and_then(|user| u32::try_from(user).ok()) {
+                self.last_unlock = Some((*method,user,at));
+                self.failed_attempts = 0;
+                events.push(LockEvent::Unlocked{method:*method,user});
+            }
+        }
+        if let Some(attempts) = frame.data.get_i64("verified_wrong").and_then(|attempts| u32::try_from(attempts).ok()) {
+            // 门锁上报的是连续失败次数, 重复的上报不会重复计入累计次数;
+            // 次数变小说明门锁通过其他方式( 机械钥匙, APP )清零之后开始了新的一轮
+            if attempts < self.failed_attempts {
+                self.total_failed += attempts;
+            } else {
+                self.total_failed += attempts - self.failed_attempts;
+            }
+            self.failed_attempts = attempts;
+            events.push(LockEvent::Rejected{attempts});
+        }
+        events
+    }
+}
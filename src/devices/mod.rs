@@ -0,0 +1,89 @@
+//!
+//! # 子设备模型
+//!
+//! 网关上报的属性都是原始字符串或数字, 这里按照设备型号转换成带单位和状态的类型:
+//!
+//! * `Climate`: 温湿度传感器 `sensor_ht` 和温湿度气压传感器 `weather.v1`
+//! * `Door`: 门窗传感器 `magnet`/`sensor_magnet.aq2`
+//! * `Hub`: 网关自身的夜灯, 光照传感器和铃声 `gateway`
+//! * `Lock`: 智能门锁 `lock.aq1`
+//! * `Motion`: 人体传感器 `motion`/`sensor_motion.aq2`
+//! * `Button`: 无线按键 `switch`/`sensor_switch.aq2`/`sensor_switch.aq3` 和无线墙壁开关 `86sw1`/`86sw2`
+//! * `Cube`: 魔方控制器 `cube`/`sensor_cube.aqgl01`
+//! * `Curtain`: 窗帘电机 `curtain`
+//! * `Plug`: 智能插座 `plug` 和墙壁插座 `ctrl_86plug`
+//! * `WallSwitch`: 单火墙壁开关 `ctrl_neutral1`/`ctrl_neutral2` 和零火墙壁开关 `ctrl_ln1`/`ctrl_ln2`
+//! * `Safety`: 烟雾报警器 `smoke`, 天然气报警器 `natgas` 和水浸传感器 `sensor_wleak.aq1`
+//! * `Vibration`: 振动传感器 `vibration`/`sensor_vibration.aq1`
+//!
+//! 有状态的设备实现 `Device`, 把同一个 sid 的报文依次交给 `update` 即可得到对应事件;
+//! 电池设备的电量由 `Battery` 统一跟踪, 所有设备的报文都可以交给同一个 `Battery`
+//!
+
+pub mod battery;
+pub mod button;
+pub mod climate;
+pub mod cube;
+pub mod curtain;
+pub mod door;
+pub mod hub;
+pub mod lock;
+pub mod motion;
+pub mod plug;
+pub mod safety;
+pub mod vibration;
+pub mod wall_switch;
+
+pub use self::battery::{Battery, BatteryCurve, BatteryEvent, BatteryLevel};
+pub use self::button::{Button, ButtonEvent, Channel, Gesture};
+pub use self::climate::Climate;
+pub use self::cube::{Cube, CubeEvent};
+pub use self::curtain::{Curtain, CurtainEvent};
+pub use self::door::{Door, DoorEvent};
+pub use self::hub::{Hub, HubEvent, Light, ParseToneError, Tone, UserTone, STOP_TONE_MID};
+pub use self::lock::{Lock, LockEvent, UnlockMethod};
+pub use self::motion::{Motion, MotionEvent};
+pub use self::plug::{Plug, PlugEvent};
+pub use self::safety::{Alarm, Safety, SafetyEvent, Sensitivity};
+pub use self::vibration::{Vibration, VibrationEvent};
+pub use self::wall_switch::{WallSwitch, WallSwitchEvent};
+
+use crate::protocol::Frame;
+use crate::types::{Model, Sid};
+use std::time::Instant;
+
+///
+/// 有状态的设备模型
+///
+pub trait Device{
+    type Event;
+
+    ///
+    /// 是否支持该型号
+    ///
+    fn supports(model:&Model)->bool;
+
+    ///
+    /// 设备 sid
+    ///
+    fn sid(&self)->&Sid;
+
+    ///
+    /// 根据报文更新设备状态, `at` 为收到报文的时间, 返回产生的事件
+    ///
+    fn update_at(&mut self,frame:&Frame,at:Instant)->Vec<Self::Event>;
+
+    ///
+    /// 根据报文更新设备状态, 以当前时间作为收到报文的时间
+    ///
+    fn update(&mut self,frame:&Frame)->Vec<Self::Event>{
+        self.update_at(frame,Instant::now())
+    }
+
+    ///
+    /// 报文是否属于该设备, `write_ack` 等报文可能没有型号
+    ///
+    fn accepts(&self,frame:&Frame)->bool{
+        frame.sid == *self.sid() && frame.model.as_ref().map_or(true,Self::supports)
+    }
+}
@@ -0,0 +1,128 @@
+use crate::devices::Device;
+use crate::prelude::DeviceStatus;
+use crate::protocol::Frame;
+use crate::types::{Model, Sid};
+use std::time::{Duration, Instant};
+
+///
+/// 人体传感器事件
+///
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub enum MotionEvent{
+    Detected, // 检测到有人活动
+    Vacant{seconds:u32}, // 持续无人活动的秒数( 120/180/300/600/1200/1800 )
+    Illuminance{lux:u32}, // 光照度变化, 只有 `sensor_motion.aq2` 支持
+}
+
+///
+/// 人体传感器( `motion`/`sensor_motion.aq2` )
+///
+/// 有人活动时上报 `status` 为 `motion`, 之后持续无人活动会依次上报 `no_motion` 及无人活动的秒数;
+/// aq2 版本还会上报光照度 `lux` 和 `illumination`
+///
+/// ```
+/// use aqara_rs::protocol::Message;
+/// use aqara_rs::devices::{Device, Motion, MotionEvent};
+/// let mut motion = Motion::new("158d0001a2b3c4".parse().unwrap());
+/// if let Message::Report(frame) = Message::parse(br#"{"cmd":"report","model":"sensor_motion.aq2","sid":"158d0001a2b3c4","data":"{\"status\":\"motion\",\"lux\":\"95\"}"}"#).unwrap() {
+///     assert_eq!(motion.update(&frame),vec![MotionEvent::Detected,MotionEvent::Illuminance{lux:95}]);
+/// }
+/// assert_eq!(motion.is_occupied(),Some(true));
+/// assert_eq!(motion.lux(),Some(95));
+/// ```
+///
+#[derive(Debug,Clone)]
+pub struct Motion{
+    sid:Sid,
+    occupied:Option<bool>,
+    last_motion:Option<Instant>,
+    lux:Option<u32>,
+    illumination:Option<u32>,
+}
+
+impl Motion{
+    pub fn new(sid:Sid)->Self{
+        Self{sid,occupied:None,last_motion:None,lux:None,illumination:None}
+    }
+
+    ///
+    /// 当前是否有人, 未收到过状态时为 `None`
+    ///
+    pub fn is_occupied(&self)->Option<bool>{
+        self.occupied
+    }
+
+    ///
+    /// 最近一次检测到有人活动的时间
+    ///
+    pub fn last_motion(&self)->Option<Instant>{
+        self.last_motion
+    }
+
+    ///
+    /// 距离最近一次有人活动的时长
+    ///
+    pub fn since_last_motion(&self,now:Instant)->Option<Duration>{
+        self.last_motion.map(|last| now.saturating_duration_since(last))
+    }
+
+    ///
+    /// 光照度( lux )
+    ///
+    pub fn lux(&self)->Option<u32>{
+        self.lux
+    }
+
+    ///
+    /// 网关上报的原始光照值
+    ///
+    pub fn illumination(&self)->Option<u32>{
+        self.illumination
+    }
+}
+
+impl Device for Motion{
+    type Event = MotionEvent;
+
+    fn supports(model:&Model)->bool{
+        model.as_str().starts_with("motion") || model.as_str().starts_with("sensor_motion")
+    }
+
+    fn sid(&self)->&Sid{
+        &self.sid
+    }
+
+    fn update_at(&mut self,frame:&Frame,at:Instant)->Vec<MotionEvent>{
+        let mut events = Vec::new();
+        if !self.accepts(frame) {
+            return events;
+        }
+        // 2.x 协议使用 motion_status
+        let status = frame.data.device_status("status").or_else(|| frame.data.device_status("motion_status"));
+        if status == Some(DeviceStatus::Motion) {
+            self.occupied = Some(true);
+            self.last_motion = Some(at);
+            events.push(MotionEvent::Detected);
+        }
+        if let Some(seconds) = frame.data.get_i64("no_motion") {
+            let seconds = seconds.max(0) as u32;
+            self.occupied = Some(false);
+            // 没有见过活动的时候按照无人时长倒推
+            if self.last_motion.is_none() {
+                self.last_motion = at.checked_sub(Duration::from_secs(u64::from(seconds)));
+            }
+            events.push(MotionEvent::Vacant{seconds});
+        }
+        if let Some(illumination) = frame.data.get_i64("illumination") {
+            self.illumination = Some(illumination.max(0) as u32);
+        }
+        if let Some(lux) = frame.data.get_i64("lux") {
+            let lux = lux.max(0) as u32;
+            if self.lux != Some(lux) {
+                self.lux = Some(lux);
+                events.push(MotionEvent::Illuminance{lux});
+            }
+        }
+        events
+    }
+}
@@ -0,0 +1,176 @@
+use crate::devices::Device;
+use crate::prelude::DeviceStatus;
+use crate::protocol::{AckError, Frame, WriteCommand};
+use crate::types::{Model, Sid};
+use std::time::Instant;
+
+///
+/// 插座事件
+///
+#[derive(Debug,Clone,PartialEq)]
+pub enum PlugEvent{
+    Switched{on:bool}, // 开关状态变化, 包括 `write_ack` 确认的新状态
+    InUse{inuse:bool}, // 是否有负载接入
+    LoadPower{watts:f32}, // 负载功率( W )
+    PowerConsumed{wh:u32}, // 累计用电量( Wh )
+    Rejected(AckError), // 网关拒绝了写入, 等待确认的状态已经撤销
+}
+
+///
+/// 智能插座和墙壁插座( `plug`/`ctrl_86plug`/`ctrl_86plug.aq1` )
+///
+/// 上报 `status`, `inuse`, `load_power` 和 `power_consumed`; 开关通过写入 `status` 完成,
+/// 生成的命令交给 `Gateway::write` 签名推送, 网关的 `write_ack` 会带回新的状态
+///
+/// ```
+/// use aqara_rs::protocol::Message;
+/// use aqara_rs::devices::{Device, Plug, PlugEvent};
+/// let mut plug = Plug::new("158d0001a2b3c4".parse().unwrap(),"plug".parse().unwrap());
+/// let command = plug.turn_on();
+/// assert_eq!(command.data().get_str("status"),Some("on"));
+/// if let Message::WriteAck(frame) = Message::parse(br#"{"cmd":"write_ack","model":"plug","sid":"158d0001a2b3c4","data":"{\"status\":\"on\",\"load_power\":\"3.21\"}"}"#).unwrap() {
+///     assert_eq!(plug.update(&frame),vec![PlugEvent::Switched{on:true},PlugEvent::LoadPower{watts:3.21}]);
+/// }
+/// assert_eq!(plug.is_on(),Some(true));
+/// assert_eq!(plug.pending(),None);
+/// ```
+///
+#[derive(Debug,Clone)]
+pub struct Plug{
+    sid:Sid,
+    model:Model,
+    on:Option<bool>,
+    pending:Option<bool>,
+    inuse:Option<bool>,
+    load_power:Option<f32>,
+    power_consumed:Option<u32>,
+}
+
+impl Plug{
+    pub fn new(sid:Sid,model:Model)->Self{
+        Self{sid,model,on:None,pending:None,inuse:None,load_power:None,power_consumed:None}
+    }
+
+    ///
+    /// 当前是否通电, 未收到过状态时为 `None`
+    ///
+    pub fn is_on(&self)->Option<bool>{
+        self.on
+    }
+
+    ///
+    /// 已经下发但还没有被网关确认的状态
+    ///
+    pub fn pending(&self)->Option<bool>{
+        self.pending
+    }
+
+    ///
+    /// 是否有负载接入
+    ///
+    pub fn is_in_use(&self)->Option<bool>{
+        self.inuse
+    }
+
+    ///
+    /// 负载功率( W )
+    ///
+    pub fn load_power(&self)->Option<f32>{
+        self.load_power
+    }
+
+    ///
+    /// 累计用电量( Wh )
+    ///
+    pub fn power_consumed(&self)->Option<u32>{
+        self.power_consumed
+    }
+
+    ///
+    /// 生成通电命令
+    ///
+    pub fn turn_on(&mut self)->WriteCommand{
+        self.switch(true)
+    }
+
+    ///
+    /// 生成断电命令
+    ///
+    pub fn turn_off(&mut self)->WriteCommand{
+        self.switch(false)
+    }
+
+    ///
+    /// 生成切换命令, 状态未知时按照断电处理, 即切换为通电
+    ///
+    pub fn toggle(&mut self)->WriteCommand{
+        let on = self.pending.or(self.on).unwrap_or(false);
+        self.switch(!on)
+    }
+
+    fn switch(&mut self,on:bool)->WriteCommand{
+        self.pending = Some(on);
+        let status = if on { DeviceStatus::On } else { DeviceStatus::Off };
+        WriteCommand::new(self.sid.clone(),self.model.clone())
+            .set("status",status.as_str())
+            .retry(true)
+    }
+}
+
+impl Device for Plug{
+    type Event = PlugEvent;
+
+    fn supports(model:&Model)->bool{
+        let model = model.as_str();
+        model == "plug" || model.starts_with("ctrl_86plug")
+    }
+
+    fn sid(&self)->&Sid{
+        &self.sid
+    }
+
+    fn update_at(&mut self,frame:&Frame,_at:Instant)->Vec<PlugEvent>{
+        let mut events = Vec::new();
+        if !self.accepts(frame) {
+            return events;
+        }
+        if let Some(error) = frame.error() {
+            self.pending = None;
+            events.push(PlugEvent::Rejected(error));
+            return events;
+        }
+        let on = match frame.data.device_status("status") {
+            Some(DeviceStatus::On) => Some(true),
+            Some(DeviceStatus::Off) => Some(false),
+            _ => None,
+        };
+        if let Some(on) = on {
+            if self.pending == Some(on) {
+                self.pending = None;
+            }
+            if self.on != Some(on) {
+                self.on = Some(on);
+                events.push(PlugEvent::Switched{on});
+            }
+        }
+        if let Some(inuse) = frame.data.get_i64("inuse").map(|value| value != 0) {
+            if self.inuse != Some(inuse) {
+                self.inuse = Some(inuse);
+                events.push(PlugEvent::InUse{inuse});
+            }
+        }
+        if let Some(watts) = frame.data.get_f64("load_power").map(|value| value as f32) {
+            if self.load_power != Some(watts) {
+                self.load_power = Some(watts);
+                events.push(PlugEvent::LoadPower{watts});
+            }
+        }
+        if let Some(wh) = frame.data.get_i64("power_consumed").map(|value| value as u32) {
+            if self.power_consumed != Some(wh) {
+                self.power_consumed = Some(wh);
+                events.push(PlugEvent::PowerConsumed{wh});
+            }
+        }
+        events
+    }
+}
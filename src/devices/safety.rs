@@ -0,0 +1,203 @@
+use crate::devices::Device;
+use crate::prelude::DeviceStatus;
+use crate::protocol::{Frame, WriteCommand};
+use crate::types::{Model, Sid};
+use std::time::Instant;
+
+///
+/// 烟雾和天然气报警器的 `alarm` 状态
+///
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+pub enum Alarm{
+    Normal, // 0: 解除报警
+    Fire, // 1: 火警或燃气泄漏报警
+    Analog, // 2: 模拟报警( 自检 )
+    BatteryFault, // 8: 电池故障报警
+    SensitivityFault, // 64: 灵敏度故障报警
+    I2cFault, // 32768: IIC 通信故障报警
+    Unknown(u32),
+}
+
+impl Alarm{
+    pub fn from_code(code:u32)->Self{
+        match code {
+            0 => Alarm::Normal,
+            1 => Alarm::Fire,
+            2 => Alarm::Analog,
+            8 => Alarm::BatteryFault,
+            64 => Alarm::SensitivityFault,
+            32768 => Alarm::I2cFault,
+            code => Alarm::Unknown(code),
+        }
+    }
+
+    pub fn code(&self)->u32{
+        match self {
+            Alarm::Normal => 0,
+            Alarm::Fire => 1,
+            Alarm::Analog => 2,
+            Alarm::BatteryFault => 8,
+            Alarm::SensitivityFault => 64,
+            Alarm::I2cFault => 32768,
+            Alarm::Unknown(code) => *code,
+        }
+    }
+
+    ///
+    /// 是否为报警( 包括模拟报警 )
+    ///
+    pub fn is_alarm(&self)->bool{
+        matches!(self,Alarm::Fire | Alarm::Analog)
+    }
+
+    ///
+    /// 是否为设备故障
+    ///
+    pub fn is_fault(&self)->bool{
+        matches!(self,Alarm::BatteryFault | Alarm::SensitivityFault | Alarm::I2cFault)
+    }
+}
+
+///
+/// 灵敏度, 天然气报警器和振动传感器通用
+///
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+pub enum Sensitivity{
+    Low = 1,
+    Medium = 2,
+    High = 3,
+}
+
+///
+/// 安全类传感器事件
+///
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub enum SafetyEvent{
+    Alarm(Alarm), // 报警状态变化
+    Density{value:u32}, // 烟雾或燃气浓度
+    Leak{leak:bool}, // 水浸状态变化
+}
+
+///
+/// 安全类传感器( 烟雾报警器 `smoke`, 天然气报警器 `natgas`, 水浸传感器 `sensor_wleak.aq1` )
+///
+/// 烟雾和天然气报警器上报 `alarm` 和 `density`, 水浸传感器通过 `status` 上报 `leak`/`no_leak`;
+/// 天然气报警器可以写入灵敏度和触发自检
+///
+/// ```
+/// use aqara_rs::protocol::Message;
+/// use aqara_rs::devices::{Device, Alarm, Safety, SafetyEvent};
+/// let mut smoke = Safety::new("158d0001a2b3c4".parse().unwrap(),"smoke".parse().unwrap());
+/// if let Message::Report(frame) = Message::parse(br#"{"cmd":"report","model":"smoke","sid":"158d0001a2b3c4","data":"{\"alarm\":\"1\",\"density\":\"12\"}"}"#).unwrap() {
+///     assert_eq!(smoke.update(&frame),vec![SafetyEvent::Alarm(Alarm::Fire),SafetyEvent::Density{value:12}]);
+/// }
+/// assert!(smoke.alarm().unwrap().is_alarm());
+/// ```
+///
+#[derive(Debug,Clone)]
+pub struct Safety{
+    sid:Sid,
+    model:Model,
+    alarm:Option<Alarm>,
+    density:Option<u32>,
+    leak:Option<bool>,
+}
+
+impl Safety{
+    pub fn new(sid:Sid,model:Model)->Self{
+        Self{sid,model,alarm:None,density:None,leak:None}
+    }
+
+    ///
+    /// 当前报警状态
+    ///
+    pub fn alarm(&self)->Option<Alarm>{
+        self.alarm
+    }
+
+    ///
+    /// 烟雾或燃气浓度
+    ///
+    pub fn density(&self)->Option<u32>{
+        self.density
+    }
+
+    ///
+    /// 是否漏水
+    ///
+    pub fn is_leaking(&self)->Option<bool>{
+        self.leak
+    }
+
+    ///
+    /// 是否为天然气报警器
+    ///
+    pub fn is_gas(&self)->bool{
+        self.model.as_str().contains("natgas")
+    }
+
+    ///
+    /// 生成设置天然气报警器灵敏度的命令, 其他型号返回 `None`
+    ///
+    pub fn set_sensitivity(&self,sensitivity:Sensitivity)->Option<WriteCommand>{
+        self.gas_command("sensitivity",sensitivity as u32)
+    }
+
+    ///
+    /// 生成天然气报警器自检命令, 其他型号返回 `None`
+    ///
+    pub fn self_test(&self)->Option<WriteCommand>{
+        self.gas_command("selftest",1)
+    }
+
+    fn gas_command(&self,key:&str,value:u32)->Option<WriteCommand>{
+        if !self.is_gas() {
+            return None;
+        }
+        Some(WriteCommand::new(self.sid.clone(),self.model.clone()).set(key,value).retry(true))
+    }
+}
+
+impl Device for Safety{
+    type Event = SafetyEvent;
+
+    fn supports(model:&Model)->bool{
+        let model = model.as_str();
+        model.contains("smoke") || model.contains("natgas") || model.starts_with("sensor_wleak")
+    }
+
+    fn sid(&self)->&Sid{
+        &self.sid
+    }
+
+    fn update_at(&mut self,frame:&Frame,_at:Instant)->Vec<SafetyEvent>{
+        let mut events = Vec::new();
+        if !self.accepts(frame) {
+            return events;
+        }
+        if let Some(alarm) = frame.data.get_i64("alarm").map(|code| Alarm::from_code(code as u32)) {
+            if self.alarm != Some(alarm) {
+                self.alarm = Some(alarm);
+                events.push(SafetyEvent::Alarm(alarm));
+            }
+        }
+        if let Some(value) = frame.data.get_i64("density").map(|value| value as u32) {
+            if self.density != Some(value) {
+                self.density = Some(value);
+                events.push(SafetyEvent::Density{value});
+            }
+        }
+        let leak = match frame.data.device_status("status") {
+            Some(DeviceStatus::Leak) => Some(true),
+            Some(DeviceStatus::NoLeak) => Some(false),
+            _ => None,
+        };
+        if let Some(leak) = leak {
+            if self.leak != Some(leak) {
+                self.leak = Some(leak);
+                events.push(SafetyEvent::Leak{leak});
+            }
+        }
+        events
+    }
+}
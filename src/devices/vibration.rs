@@ -0,0 +1,127 @@
+use crate::devices::{Device, Sensitivity};
+use crate::prelude::DeviceStatus;
+use crate::protocol::{Frame, WriteCommand};
+use crate::types::{Model, Sid};
+use std::time::Instant;
+
+///
+/// 振动传感器事件
+///
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub enum VibrationEvent{
+    Vibrate, // 振动
+    Tilt, // 倾斜
+    FreeFall, // 跌落
+    TiltAngle{degrees:i32}, // 倾斜之后的最终角度
+    Coordination{x:i32,y:i32,z:i32}, // 三轴加速度
+    BedActivity{value:u32}, // 床活动量
+}
+
+///
+/// 振动传感器( `vibration`/`sensor_vibration.aq1` )
+///
+/// 动作通过 `status` 上报 `vibrate`/`tilt`/`free_fall`, 倾斜之后会上报 `final_tilt_angle`,
+/// 另外还有三轴加速度 `coordination`( 格式为 `x,y,z` )和床活动量 `bed_activity`
+///
+/// ```
+/// use aqara_rs::protocol::Message;
+/// use aqara_rs::devices::{Device, Vibration, VibrationEvent};
+/// let mut vibration = Vibration::new("158d0001a2b3c4".parse().unwrap(),"vibration".parse().unwrap());
+/// if let Message::Report(frame) = Message::parse(br#"{"cmd":"report","model":"vibration","sid":"158d0001a2b3c4","data":"{\"coordination\":\"10,-12,1020\"}"}"#).unwrap() {
+///     assert_eq!(vibration.update(&frame),vec![VibrationEvent::Coordination{x:10,y:-12,z:1020}]);
+/// }
+/// assert_eq!(vibration.coordination(),Some((10,-12,1020)));
+/// ```
+///
+#[derive(Debug,Clone)]
+pub struct Vibration{
+    sid:Sid,
+    model:Model,
+    tilt_angle:Option<i32>,
+    coordination:Option<(i32,i32,i32)>,
+    bed_activity:Option<u32>,
+}
+
+impl Vibration{
+    pub fn new(sid:Sid,model:Model)->Self{
+        Self{sid,model,tilt_angle:None,coordination:None,bed_activity:None}
+    }
+
+    ///
+    /// 最近一次倾斜的最终角度
+    ///
+    pub fn tilt_angle(&self)->Option<i32>{
+        self.tilt_angle
+    }
+
+    ///
+    /// 三轴加速度 `(x,y,z)`
+    ///
+    pub fn coordination(&self)->Option<(i32,i32,i32)>{
+        self.coordination
+    }
+
+    ///
+    /// 床活动量
+    ///
+    pub fn bed_activity(&self)->Option<u32>{
+        self.bed_activity
+    }
+
+    ///
+    /// 生成设置灵敏度的命令
+    ///
+    pub fn set_sensitivity(&self,sensitivity:Sensitivity)->WriteCommand{
+        WriteCommand::new(self.sid.clone(),self.model.clone())
+            .set("sensitivity",sensitivity as u32)
+            .retry(true)
+    }
+
+    fn parse_coordination(value:&str)->Option<(i32,i32,i32)>{
+        let mut axes = value.split(',').map(|axis| axis.trim().parse::<i32>());
+        let coordination = (axes.next()?.ok()?,axes.next()?.ok()?,axes.next()?.ok()?);
+        if axes.next().is_some() {
+            return None;
+        }
+        Some(coordination)
+    }
+}
+
+impl Device for Vibration{
+    type Event = VibrationEvent;
+
+    fn supports(model:&Model)->bool{
+        let model = model.as_str();
+        model == "vibration" || model.starts_with("sensor_vibration")
+    }
+
+    fn sid(&self)->&Sid{
+        &self.sid
+    }
+
+    fn update_at(&mut self,frame:&Frame,_at:Instant)->Vec<VibrationEvent>{
+        let mut events = Vec::new();
+        if !self.accepts(frame) {
+            return events;
+        }
+        match frame.data.device_status("status") {
+            Some(DeviceStatus::Vibrate) => events.push(VibrationEvent::Vibrate),
+            Some(DeviceStatus::Tilt) => events.push(VibrationEvent::Tilt),
+            Some(DeviceStatus::FreeFall) => events.push(VibrationEvent::FreeFall),
+            _ => {},
+        }
+        if let Some(degrees) = frame.data.get_i64("final_tilt_angle").map(|value| value as i32) {
+            self.tilt_angle = Some(degrees);
+            events.push(VibrationEvent::TiltAngle{degrees});
+        }
+        if let Some((x,y,z)) = frame.data.get_str("coordination").and_then(Self::parse_coordination) {
+            self.coordination = Some((x,y,z));
+            events.push(VibrationEvent::Coordination{x,y,z});
+        }
+        if let Some(value) = frame.data.get_i64("bed_activity").map(|value| value as u32) {
+            self.bed_activity = Some(value);
+            events.push(VibrationEvent::BedActivity{value});
+        }
+        events
+    }
+}
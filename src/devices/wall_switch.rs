@@ -0,0 +1,197 @@
+use crate::devices::{Channel, Device};
+use crate::prelude::DeviceStatus;
+use crate::protocol::{AckError, Frame, WriteCommand};
+use crate::types::{Model, Sid};
+use std::time::Instant;
+
+const CHANNEL_KEYS:[&str;2] = ["channel_0","channel_1"];
+
+///
+/// 墙壁开关事件
+///
+#[derive(Debug,Clone,PartialEq)]
+pub enum WallSwitchEvent{
+    Switched{channel:Channel,on:bool}, // 通道开关状态变化
+    LoadPower{watts:f32}, // 负载功率( W ), 只有零火版支持
+    PowerConsumed{wh:u32}, // 累计用电量( Wh ), 只有零火版支持
+    Rejected(AckError), // 网关拒绝了写入, 所有通道等待确认的状态已经撤销
+}
+
+///
+/// 墙壁开关( 单火版 `ctrl_neutral1`/`ctrl_neutral2`, 零火版 `ctrl_ln1`/`ctrl_ln2` )
+///
+/// 单键开关只有 `channel_0`, 双键开关还有 `channel_1`; `Channel::Both` 表示同时操作所有通道,
+/// 单键开关不能操作 `Channel::Channel1`.
+/// 零火版额外上报 `load_power` 和 `power_consumed`
+///
+/// ```
+/// use aqara_rs::protocol::Message;
+/// use aqara_rs::devices::{Device, Channel, WallSwitch, WallSwitchEvent};
+/// let mut switch = WallSwitch::new("158d0001a2b3c4".parse().unwrap(),"ctrl_neutral2".parse().unwrap());
+/// let command = switch.turn_on(Channel::Both).unwrap();
+/// assert_eq!(command.data().get_str("channel_1"),Some("on"));
+/// if let Message::Report(frame) = Message::parse(br#"{"cmd":"report","model":"ctrl_neutral2","sid":"158d0001a2b3c4","data":"{\"channel_1\":\"on\"}"}"#).unwrap() {
+///     assert_eq!(switch.update(&frame),vec![WallSwitchEvent::Switched{channel:Channel::Channel1,on:true}]);
+/// }
+/// ```
+///
+#[derive(Debug,Clone)]
+pub struct WallSwitch{
+    sid:Sid,
+    model:Model,
+    states:[Option<bool>;2],
+    pending:[Option<bool>;2],
+    load_power:Option<f32>,
+    power_consumed:Option<u32>,
+}
+
+impl WallSwitch{
+    pub fn new(sid:Sid,model:Model)->Self{
+        Self{sid,model,states:[None;2],pending:[None;2],load_power:None,power_consumed:None}
+    }
+
+    ///
+    /// 通道数量, 按照去掉版本后缀的型号判断( `ctrl_ln2.aq1` 为双键 )
+    ///
+    pub fn channels(&self)->usize{
+        let base = self.model.as_str().split('.').next().unwrap_or_default();
+        if base.ends_with('2') { 2 } else { 1 }
+    }
+
+    ///
+    /// 是否为零火版, 零火版会上报功率
+    ///
+    pub fn is_live_neutral(&self)->bool{
+        self.model.as_str().starts_with("ctrl_ln")
+    }
+
+    ///
+    /// 通道是否打开, `Channel::Both` 需要所有通道都打开; 型号没有该通道时为 `None`
+    ///
+    pub fn is_on(&self,channel:Channel)->Option<bool>{
+        let mut on = true;
+        for index in self.indexes(channel)? {
+            on &= self.states[index]?;
+        }
+        Some(on)
+    }
+
+    ///
+    /// 已经下发但还没有被网关确认的通道状态
+    ///
+    pub fn pending(&self,channel:Channel)->Option<bool>{
+        let mut on = true;
+        for index in self.indexes(channel)? {
+            on &= self.pending[index]?;
+        }
+        Some(on)
+    }
+
+    ///
+    /// 负载功率( W )
+    ///
+    pub fn load_power(&self)->Option<f32>{
+        self.load_power
+    }
+
+    ///
+    /// 累计用电量( Wh )
+    ///
+    pub fn power_consumed(&self)->Option<u32>{
+        self.power_consumed
+    }
+
+    ///
+    /// 生成打开通道的命令, 型号没有该通道时返回 `None`
+    ///
+    pub fn turn_on(&mut self,channel:Channel)->Option<WriteCommand>{
+        self.switch(channel,true)
+    }
+
+    ///
+    /// 生成关闭通道的命令, 型号没有该通道时返回 `None`
+    ///
+    pub fn turn_off(&mut self,channel:Channel)->Option<WriteCommand>{
+        self.switch(channel,false)
+    }
+
+    ///
+    /// 生成切换通道的命令, 状态未知时切换为打开; 型号没有该通道时返回 `None`
+    ///
+    pub fn toggle(&mut self,channel:Channel)->Option<WriteCommand>{
+        let on = self.pending(channel).or_else(|| self.is_on(channel)).unwrap_or(false);
+        self.switch(channel,!on)
+    }
+
+    fn indexes(&self,channel:Channel)->Option<std::ops::Range<usize>>{
+        match channel {
+            Channel::Channel0 => Some(0..1),
+            Channel::Channel1 if self.channels() > 1 => Some(1..2),
+            Channel::Channel1 => None,
+            Channel::Both => Some(0..self.channels()),
+        }
+    }
+
+    fn switch(&mut self,channel:Channel,on:bool)->Option<WriteCommand>{
+        let status = if on { DeviceStatus::On } else { DeviceStatus::Off };
+        let mut command = WriteCommand::new(self.sid.clone(),self.model.clone()).retry(true);
+        for index in self.indexes(channel)? {
+            self.pending[index] = Some(on);
+            command = command.set(CHANNEL_KEYS[index],status.as_str());
+        }
+        Some(command)
+    }
+}
+
+impl Device for WallSwitch{
+    type Event = WallSwitchEvent;
+
+    fn supports(model:&Model)->bool{
+        let model = model.as_str();
+        model.starts_with("ctrl_neutral") || model.starts_with("ctrl_ln")
+    }
+
+    fn sid(&self)->&Sid{
+        &self.sid
+    }
+
+    fn update_at(&mut self,frame:&Frame,_at:Instant)->Vec<WallSwitchEvent>{
+        let mut events = Vec::new();
+        if !self.accepts(frame) {
+            return events;
+        }
+        if let Some(error) = frame.error() {
+            self.pending = [None;2];
+            events.push(WallSwitchEvent::Rejected(error));
+            return events;
+        }
+        let channels = [Channel::Channel0,Channel::Channel1];
+        for (index,channel) in channels.iter().enumerate() {
+            let on = match frame.data.device_status(CHANNEL_KEYS[index]) {
+                Some(DeviceStatus::On) => true,
+                Some(DeviceStatus::Off) => false,
+                _ => continue,
+            };
+            if self.pending[index] == Some(on) {
+                self.pending[index] = None;
+            }
+            if self.states[index] != Some(on) {
+                self.states[index] = Some(on);
+                events.push(WallSwitchEvent::Switched{channel:*channel,on});
+            }
+        }
+        if let Some(watts) = frame.data.get_f64("load_power").map(|value| value as f32) {
+            if self.load_power != Some(watts) {
+                self.load_power = Some(watts);
+                events.push(WallSwitchEvent::LoadPower{watts});
+            }
+        }
+        if let Some(wh) = frame.data.get_i64("power_consumed").map(|value| value as u32) {
+            if self.power_consumed != Some(wh) {
+                self.power_consumed = Some(wh);
+                events.push(WallSwitchEvent::PowerConsumed{wh});
+            }
+        }
+        events
+    }
+}
@@ -3,4 +3,7 @@ pub mod prelude;
 pub mod builder;
 pub mod device;
 pub mod session;
+pub mod protocol;
+pub mod types;
+pub mod devices;
 
@@ -1,4 +1,7 @@
 use crate::session::{Multicast, Broadcast, Unicast};
+use crate::device::Peer;
+use crate::protocol::{Frame, Iam, Message, ProtocolError};
+use crate::types::Sid;
 
 ///
 /// 心跳反馈的字节长度: 16
@@ -17,11 +20,6 @@ pub const INITIALIZE_AES_KEY_IV:[u8;AES_KEY_SIZE] = [
     0x17, 0x99, 0x6d, 0x09, 0x3d, 0x28, 0xdd, 0xb3, 0xba, 0x69, 0x5a, 0x2e, 0x6f, 0x58, 0x56, 0x2e
 ];
 
-///
-/// 硬编码 whois 命令
-///
-pub const COMMAND_WHOIS: &'static str = "{ \"cmd\":\"whois\"}";
-
 ///
 /// 默认网关组播地址
 ///
@@ -42,6 +40,11 @@ pub const DEFAULT_UNICAST_ADDRESS: std::net::Ipv4Addr = std::net::Ipv4Addr::UNSP
 ///
 pub const DEFAULT_UNICAST_PORT:u16 = 9898;
 
+///
+/// 网关收到 `join_permission` 之后打开配对窗口的时长, 由网关固件决定
+///
+pub const GATEWAY_PAIRING_WINDOW: std::time::Duration = std::time::Duration::from_secs(30);
+
 
 ///
 /// 定义简单的错误处理 BOX
@@ -61,18 +64,152 @@ pub trait ResponseEvent{
 }
 
 
+///
+/// 协议事件回调
+///
+/// 和 `ResponseEvent` 按照传输方式区分不同, 这里按照协议命令区分, 回调收到的是已经解析好的报文和发送报文的网关,
+/// 通过 `Gateway::run_event` 注册. 网关尚未应答 `iam` 之前, `Peer` 只有地址信息
+///
+pub trait MessageEvent{
+    fn on_iam(&self,_peer:&Peer,_iam:&Iam){}
+    fn on_report(&self,_peer:&Peer,_frame:&Frame){}
+    fn on_heartbeat(&self,_peer:&Peer,_frame:&Frame){}
+    fn on_ack(&self,_peer:&Peer,_message:&Message){} // get_id_list_ack/read_ack/write_ack
+    fn on_message(&self,_peer:&Peer,_message:&Message){} // 其他命令, 一般是局域网内其他服务器发出的请求
+    fn on_error(&self,_peer:&Peer,_error:&ProtocolError){}
+    fn on_join(&self,_peer:&Peer,_sid:&Sid){} // 配对窗口内新入网的子设备
+}
+
+
 ///
 /// 设备状态
 ///
-#[derive(Debug)]
+/// 对应报文属性 `status`/`channel_0`/`channel_1` 等的取值, 通过 `FromStr`/`Display` 和报文字符串互相转换,
+/// 无法识别的状态保存在 `Unknown` 里
+///
+/// ```
+/// use aqara_rs::prelude::DeviceStatus;
+/// let status:DeviceStatus = "long_click_press".parse().unwrap();
+/// assert_eq!(status,DeviceStatus::LongClickPress);
+/// assert_eq!(status.to_string(),"long_click_press");
+/// assert_eq!("fly".parse::<DeviceStatus>().unwrap(),DeviceStatus::Unknown("fly".to_string()));
+/// ```
+///
+#[derive(Debug,Clone,PartialEq,Eq,Hash)]
 pub enum DeviceStatus{
-    Unknown, // 未知命令
+    Unknown(String), // 未知状态
     Open, // 开放
     Close, // 关闭
     Motion, // 被人触发动作
+    NoMotion, // 一段时间无人活动
     Click, // 点击
     DoubleClick, // 双击
     BothClick,// 左右键同时按下
+    LongClick, // 长按
+    LongClickPress, // 长按开始
+    LongClickRelease, // 长按松开
+    LongBothClick, // 左右键同时长按
     On, // 开启
     Off, // 关闭
-}
\ No newline at end of file
+    Stop, // 停止( 窗帘 )
+    Auto, // 自动( 窗帘 )
+    Leak, // 漏水
+    NoLeak, // 未漏水
+    Shake, // 摇一摇( 按钮 )
+    ShakeAir, // 空中摇晃( 魔方 )
+    Flip90, // 翻转 90 度
+    Flip180, // 翻转 180 度
+    Move, // 平移
+    TapTwice, // 双击桌面
+    Swing, // 摆动
+    Alert, // 唤醒提醒
+    FreeFall, // 自由落体
+    Tilt, // 倾斜
+    Vibrate, // 震动
+    Iam, // 设备唤醒上报
+}
+
+impl DeviceStatus{
+    ///
+    /// 对应的报文字符串
+    ///
+    pub fn as_str(&self)->&str{
+        match self {
+            DeviceStatus::Unknown(status) => status.as_str(),
+            DeviceStatus::Open => "open",
+            DeviceStatus::Close => "close",
+            DeviceStatus::Motion => "motion",
+            DeviceStatus::NoMotion => "no_motion",
+            DeviceStatus::Click => "click",
+            DeviceStatus::DoubleClick => "double_click",
+            DeviceStatus::BothClick => "both_click",
+            DeviceStatus::LongClick => "long_click",
+            DeviceStatus::LongClickPress => "long_click_press",
+            DeviceStatus::LongClickRelease => "long_click_release",
+            DeviceStatus::LongBothClick => "long_both_click",
+            DeviceStatus::On => "on",
+            DeviceStatus::Off => "off",
+            DeviceStatus::Stop => "stop",
+            DeviceStatus::Auto => "auto",
+            DeviceStatus::Leak => "leak",
+            DeviceStatus::NoLeak => "no_leak",
+            DeviceStatus::Shake => "shake",
+            DeviceStatus::ShakeAir => "shake_air",
+            DeviceStatus::Flip90 => "flip90",
+            DeviceStatus::Flip180 => "flip180",
+            DeviceStatus::Move => "move",
+            DeviceStatus::TapTwice => "tap_twice",
+            DeviceStatus::Swing => "swing",
+            DeviceStatus::Alert => "alert",
+            DeviceStatus::FreeFall => "free_fall",
+            DeviceStatus::Tilt => "tilt",
+            DeviceStatus::Vibrate => "vibrate",
+            DeviceStatus::Iam => "iam",
+        }
+    }
+}
+
+impl std::str::FromStr for DeviceStatus{
+    type Err = std::convert::Infallible;
+
+    fn from_str(s:&str)->Result<Self,Self::Err>{
+        Ok(match s {
+            "open" => DeviceStatus::Open,
+            "close" => DeviceStatus::Close,
+            "motion" => DeviceStatus::Motion,
+            "no_motion" => DeviceStatus::NoMotion,
+            "click" => DeviceStatus::Click,
+            "double_click" => DeviceStatus::DoubleClick,
+            "both_click" => DeviceStatus::BothClick,
+            "long_click" => DeviceStatus::LongClick,
+            "long_click_press" => DeviceStatus::LongClickPress,
+            "long_click_release" => DeviceStatus::LongClickRelease,
+            "long_both_click" => DeviceStatus::LongBothClick,
+            "on" => DeviceStatus::On,
+            "off" => DeviceStatus::Off,
+            "stop" => DeviceStatus::Stop,
+            "auto" => DeviceStatus::Auto,
+            "leak" => DeviceStatus::Leak,
+            "no_leak" => DeviceStatus::NoLeak,
+            "shake" => DeviceStatus::Shake,
+            "shake_air" => DeviceStatus::ShakeAir,
+            "flip90" => DeviceStatus::Flip90,
+            "flip180" => DeviceStatus::Flip180,
+            "move" => DeviceStatus::Move,
+            "tap_twice" => DeviceStatus::TapTwice,
+            "swing" => DeviceStatus::Swing,
+            "alert" => DeviceStatus::Alert,
+            "free_fall" => DeviceStatus::FreeFall,
+            "tilt" => DeviceStatus::Tilt,
+            "vibrate" => DeviceStatus::Vibrate,
+            "iam" => DeviceStatus::Iam,
+            other => DeviceStatus::Unknown(other.to_string()),
+        })
+    }
+}
+
+impl std::fmt::Display for DeviceStatus{
+    fn fmt(&self,f:&mut std::fmt::Formatter<'_>)->std::fmt::Result{
+        f.write_str(self.as_str())
+    }
+}
@@ -0,0 +1,841 @@
+//!
+//! # 局域网协议报文
+//!
+//! 网关与服务器之间交换的都是 `JSON` 报文, 通过 `cmd` 字段区分命令类型:
+//!
+//! <pre>
+//!  服务器                                   网关
+//!    |  ------------ whois ------------->    |
+//!    |  <----------- iam ----------------    |
+//!    |  ------------ get_id_list ------->    |
+//!    |  <----------- get_id_list_ack ----    |
+//!    |  ------------ read -------------->    |
+//!    |  <----------- read_ack -----------    |
+//!    |  ------------ write ------------->    |
+//!    |  <----------- write_ack ----------    |
+//!    |  <----------- report -------------    |  设备状态变化主动上报
+//!    |  <----------- heartbeat ----------    |  网关/设备定时心跳
+//! </pre>
+//!
+//! 这里将报文统一解析成 `Message`, 避免调用方各自手动解析 `JSON`
+//!
+
+use json::JsonValue;
+use crate::builder::KeyBuilder;
+use crate::types::{Model, ShortId, Sid, Token};
+use crate::prelude::DeviceStatus;
+use std::str::FromStr;
+
+///
+/// 协议解析错误
+///
+#[derive(Debug)]
+pub enum ProtocolError{
+    Utf8(std::str::Utf8Error), // 报文不是 UTF-8 字符串
+    Json(json::Error), // 报文不是合法 JSON
+    MissingField(&'static str), // 缺少必须的字段
+    InvalidField(&'static str), // 字段类型或者内容错误
+    InvalidData(String), // data 内层的 JSON 字符串错误
+}
+
+impl std::fmt::Display for ProtocolError{
+    fn fmt(&self,f:&mut std::fmt::Formatter<'_>)->std::fmt::Result{
+        match self {
+            ProtocolError::Utf8(e) => write!(f,"message is not utf-8: {}",e),
+            ProtocolError::Json(e) => write!(f,"message is not json: {}",e),
+            ProtocolError::MissingField(name) => write!(f,"missing field `{}`",name),
+            ProtocolError::InvalidField(name) => write!(f,"invalid field `{}`",name),
+            ProtocolError::InvalidData(reason) => write!(f,"invalid data payload: {}",reason),
+        }
+    }
+}
+
+impl std::error::Error for ProtocolError{}
+
+impl From<std::str::Utf8Error> for ProtocolError{
+    fn from(e:std::str::Utf8Error)->Self{
+        ProtocolError::Utf8(e)
+    }
+}
+
+impl From<json::Error> for ProtocolError{
+    fn from(e:json::Error)->Self{
+        ProtocolError::Json(e)
+    }
+}
+
+
+///
+/// 网关应答的错误
+///
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub enum AckError{
+    InvalidKey, // 签名错误或者 token 已经过期
+    InvalidDevice, // 网关下没有该设备
+    Other(String), // 其他错误
+}
+
+impl From<&str> for AckError{
+    fn from(error:&str)->Self{
+        match error {
+            "Invalid key" => AckError::InvalidKey,
+            "Invalid device" => AckError::InvalidDevice,
+            other => AckError::Other(other.to_string()),
+        }
+    }
+}
+
+impl std::fmt::Display for AckError{
+    fn fmt(&self,f:&mut std::fmt::Formatter<'_>)->std::fmt::Result{
+        match self {
+            AckError::InvalidKey => f.write_str("Invalid key"),
+            AckError::InvalidDevice => f.write_str("Invalid device"),
+            AckError::Other(error) => f.write_str(error),
+        }
+    }
+}
+
+impl std::error::Error for AckError{}
+
+
+///
+/// 网关协议版本
+///
+/// * V1: `data` 字段为 JSON 字符串, 使用 `get_id_list` 和 `*_ack`
+/// * V2: `params` 字段为键值对数组, 使用 `discovery` 和 `*_rsp`
+///
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash,Default)]
+pub enum Version{
+    #[default]
+    V1,
+    V2,
+}
+
+impl Version{
+    ///
+    /// 根据 `iam` 报文的 `proto_version` 判断协议版本, 如 `1.1.2` => V1, `2.0.1` => V2
+    ///
+    /// ```
+    /// use aqara_rs::protocol::Version;
+    /// assert_eq!(Version::from_proto("2.0.1"),Some(Version::V2));
+    /// ```
+    ///
+    pub fn from_proto(proto_version:&str)->Option<Self>{
+        match proto_version.trim().split('.').next()?.parse::<u32>().ok()? {
+            1 => Some(Version::V1),
+            2 => Some(Version::V2),
+            _ => None,
+        }
+    }
+}
+
+
+///
+/// 网关对 `whois` 的应答
+///
+#[derive(Debug,Clone,PartialEq)]
+pub struct Iam{
+    pub ip:String,
+    pub port:u16,
+    pub sid:Sid,
+    pub model:Model,
+    pub proto_version:Option<String>,
+    pub extra:JsonValue, // 未识别的字段
+}
+
+///
+/// 网关对 `get_id_list` 的应答, 包含网关下所有子设备的 sid
+///
+#[derive(Debug,Clone,PartialEq)]
+pub struct IdList{
+    pub sid:Sid,
+    pub token:Token,
+    pub devices:Vec<Sid>,
+    pub extra:JsonValue, // 未识别的字段
+}
+
+///
+/// 设备报文, `read_ack`/`write`/`write_ack`/`report`/`heartbeat` 共用该结构
+///
+/// 网关发出的 `data` 是序列化之后的 JSON 字符串, 解析时会自动展开成 `Properties`;
+/// 网关固件新增的外层字段保存在 `extra`, 重新编码时会原样写回
+///
+#[derive(Debug,Clone,PartialEq)]
+pub struct Frame{
+    pub model:Option<Model>,
+    pub sid:Sid,
+    pub short_id:Option<ShortId>,
+    pub token:Option<Token>,
+    pub data:Properties,
+    pub extra:JsonValue, // 未识别的字段
+}
+
+///
+/// 设备属性表
+///
+/// 保存 `data` 内层的所有键值对( 包括尚未识别的属性 ), 常用属性提供了类型化的读取方法:
+/// ```
+/// use aqara_rs::protocol::Properties;
+/// let data = r#"{"voltage":3015,"temperature":"2350"}"#;
+/// let props = Properties::from_json(&data.into()).unwrap();
+/// assert_eq!(props.voltage(),Some(3015));
+/// assert_eq!(props.temperature(),Some(2350));
+/// ```
+///
+#[derive(Debug,Clone,PartialEq)]
+pub struct Properties{
+    entries:JsonValue,
+}
+
+///
+/// 局域网协议报文
+///
+#[derive(Debug,Clone,PartialEq)]
+pub enum Message{
+    Whois{extra:JsonValue}, // 查询网关
+    Iam(Iam), // 网关应答
+    GetIdList{extra:JsonValue}, // 查询子设备列表
+    GetIdListAck(IdList), // 子设备列表应答
+    Read{sid:Sid,extra:JsonValue}, // 读取设备
+    ReadAck(Frame), // 读取应答
+    Write(Frame), // 写入设备
+    WriteAck(Frame), // 写入应答
+    Report(Frame), // 设备上报
+    Heartbeat(Frame), // 心跳
+    Unknown{cmd:String,raw:Vec<u8>}, // 无法识别的命令, 保留原始报文
+}
+
+
+impl Message{
+
+    ///
+    /// 查询网关的 `whois` 报文
+    ///
+    pub fn whois()->Self{
+        Message::Whois{extra:JsonValue::new_object()}
+    }
+
+    ///
+    /// 查询子设备列表的 `get_id_list` 报文
+    ///
+    pub fn get_id_list()->Self{
+        Message::GetIdList{extra:JsonValue::new_object()}
+    }
+
+    ///
+    /// 读取设备的 `read` 报文
+    ///
+    pub fn read(sid:Sid)->Self{
+        Message::Read{sid,extra:JsonValue::new_object()}
+    }
+
+    ///
+    /// 解析网关推送过来的数据报文
+    ///
+    /// ```
+    /// use aqara_rs::protocol::Message;
+    /// let msg = Message::parse(b"{\"cmd\":\"whois\"}").unwrap();
+    /// assert_eq!(msg,Message::whois());
+    /// ```
+    ///
+    pub fn parse(buf:&[u8])->Result<Self,ProtocolError>{
+        let text = std::str::from_utf8(buf)?;
+        match Self::from_json(&json::parse(text)?)? {
+            Message::Unknown{cmd,..} => Ok(Message::Unknown{cmd,raw:buf.to_vec()}),
+            message => Ok(message),
+        }
+    }
+
+    ///
+    /// 从已经解析的 JSON 对象构建报文, 同时兼容 1.x 和 2.x 协议
+    ///
+    /// 无法识别的命令不会报错, 而是返回 `Message::Unknown`
+    ///
+    pub fn from_json(value:&JsonValue)->Result<Self,ProtocolError>{
+        let cmd = value["cmd"].as_str().ok_or(ProtocolError::MissingField("cmd"))?;
+        Ok(match cmd {
+            "whois" => Message::Whois{extra: extra(value,&["cmd"])},
+            "iam" => Message::Iam(Iam{
+                ip: required_str(value,"ip")?,
+                port: std::convert::TryFrom::try_from(required_u32(value,"port")?)
+                    .map_err(|_| ProtocolError::InvalidField("port"))?,
+                sid: required_id(value,"sid")?,
+                model: required_id(value,"model")?,
+                proto_version: value["proto_version"].as_str().map(String::from),
+                extra: extra(value,&["cmd","ip","port","sid","model","proto_version"]),
+            }),
+            "get_id_list" | "discovery" => Message::GetIdList{extra: extra(value,&["cmd"])},
+            "get_id_list_ack" => Message::GetIdListAck(IdList{
+                sid: required_id(value,"sid")?,
+                token: required_id(value,"token")?,
+                devices: id_list(&value["data"])?,
+                extra: extra(value,&["cmd","sid","token","data"]),
+            }),
+            "discovery_rsp" => Message::GetIdListAck(IdList{
+                sid: required_id(value,"sid")?,
+                token: required_id(value,"token")?,
+                devices: dev_list(&value["dev_list"])?,
+                extra: extra(value,&["cmd","sid","token","dev_list"]),
+            }),
+            "read" => Message::Read{
+                sid: required_id(value,"sid")?,
+                extra: extra(value,&["cmd","sid"]),
+            },
+            "read_ack" | "read_rsp" => Message::ReadAck(Frame::from_json(value)?),
+            "write" => Message::Write(Frame::from_json(value)?),
+            "write_ack" | "write_rsp" => Message::WriteAck(Frame::from_json(value)?),
+            "report" => Message::Report(Frame::from_json(value)?),
+            "heartbeat" => Message::Heartbeat(Frame::from_json(value)?),
+            other => Message::Unknown{cmd:other.to_string(),raw:value.dump().into_bytes()},
+        })
+    }
+
+    ///
+    /// 报文对应的 cmd 命令名( 1.x 协议 )
+    ///
+    pub fn cmd(&self)->&str{
+        self.cmd_with(Version::V1)
+    }
+
+    ///
+    /// 指定协议版本下报文对应的 cmd 命令名
+    ///
+    pub fn cmd_with(&self,version:Version)->&str{
+        match (self,version) {
+            (Message::Whois{..},_) => "whois",
+            (Message::Iam(_),_) => "iam",
+            (Message::GetIdList{..},Version::V1) => "get_id_list",
+            (Message::GetIdList{..},Version::V2) => "discovery",
+            (Message::GetIdListAck(_),Version::V1) => "get_id_list_ack",
+            (Message::GetIdListAck(_),Version::V2) => "discovery_rsp",
+            (Message::Read{..},_) => "read",
+            (Message::ReadAck(_),Version::V1) => "read_ack",
+            (Message::ReadAck(_),Version::V2) => "read_rsp",
+            (Message::Write(_),_) => "write",
+            (Message::WriteAck(_),Version::V1) => "write_ack",
+            (Message::WriteAck(_),Version::V2) => "write_rsp",
+            (Message::Report(_),_) => "report",
+            (Message::Heartbeat(_),_) => "heartbeat",
+            (Message::Unknown{cmd,..},_) => cmd.as_str(),
+        }
+    }
+
+    ///
+    /// 服务器写入的报文, `data` 直接以 JSON 对象发送
+    ///
+    fn is_write(&self)->bool{
+        matches!(self,Message::Write(_))
+    }
+
+    ///
+    /// 转化成 JSON 对象( 1.x 协议 )
+    ///
+    pub fn to_json(&self)->JsonValue{
+        self.to_json_with(Version::V1)
+    }
+
+    ///
+    /// 按照指定协议版本转化成 JSON 对象
+    ///
+    pub fn to_json_with(&self,version:Version)->JsonValue{
+        if let Message::Unknown{cmd,raw} = self {
+            return std::str::from_utf8(raw).ok()
+                .and_then(|text| json::parse(text).ok())
+                .unwrap_or_else(|| json::object!{"cmd" => cmd.as_str()});
+        }
+        let mut value = JsonValue::new_object();
+        value["cmd"] = self.cmd_with(version).into();
+        match self {
+            Message::Whois{extra} | Message::GetIdList{extra} => write_extra(&mut value,extra),
+            Message::Iam(iam) => {
+                value["ip"] = iam.ip.as_str().into();
+                value["port"] = iam.port.to_string().into();
+                value["sid"] = iam.sid.as_str().into();
+                value["model"] = iam.model.as_str().into();
+                if let Some(version) = &iam.proto_version {
+                    value["proto_version"] = version.as_str().into();
+                }
+                write_extra(&mut value,&iam.extra);
+            },
+            Message::GetIdListAck(list) => {
+                value["sid"] = list.sid.as_str().into();
+                value["token"] = list.token.as_str().into();
+                match version {
+                    Version::V1 => {
+                        let devices:Vec<JsonValue> = list.devices.iter().map(|sid| sid.as_str().into()).collect();
+                        value["data"] = JsonValue::Array(devices).dump().into();
+                    },
+                    Version::V2 => {
+                        let devices:Vec<JsonValue> = list.devices.iter().map(|sid| json::object!{"sid" => sid.as_str()}).collect();
+                        value["dev_list"] = JsonValue::Array(devices);
+                    },
+                }
+                write_extra(&mut value,&list.extra);
+            },
+            Message::Read{sid,extra} => {
+                value["sid"] = sid.as_str().into();
+                write_extra(&mut value,extra);
+            },
+            Message::ReadAck(frame)
+            | Message::Write(frame)
+            | Message::WriteAck(frame)
+            | Message::Report(frame)
+            | Message::Heartbeat(frame) => frame.write_json(&mut value,version,self.is_write()),
+            Message::Unknown{..} => {},
+        }
+        value
+    }
+
+    ///
+    /// 转化成可以直接推送的报文字节( 1.x 协议 )
+    ///
+    /// ```
+    /// use aqara_rs::protocol::Message;
+    /// assert_eq!(Message::whois().to_bytes(),b"{\"cmd\":\"whois\"}".to_vec());
+    /// ```
+    ///
+    pub fn to_bytes(&self)->Vec<u8>{
+        self.to_bytes_with(Version::V1)
+    }
+
+    ///
+    /// 按照指定协议版本转化成报文字节
+    ///
+    /// ```
+    /// use aqara_rs::protocol::{Message, Version};
+    /// assert_eq!(Message::get_id_list().to_bytes_with(Version::V2),b"{\"cmd\":\"discovery\"}".to_vec());
+    /// ```
+    ///
+    pub fn to_bytes_with(&self,version:Version)->Vec<u8>{
+        match self {
+            Message::Unknown{raw,..} => raw.clone(),
+            _ => self.to_json_with(version).dump().into_bytes(),
+        }
+    }
+}
+
+impl Frame{
+    fn from_json(value:&JsonValue)->Result<Self,ProtocolError>{
+        // short_id 部分固件为数字, 部分为字符串
+        let short_id = match &value["short_id"] {
+            JsonValue::Null => None,
+            id => Some(as_u32(id)
+                .and_then(|id| std::convert::TryFrom::try_from(id).ok())
+                .map(ShortId)
+                .ok_or(ProtocolError::InvalidField("short_id"))?),
+        };
+        Ok(Self{
+            model: optional_id(value,"model")?,
+            sid: required_id(value,"sid")?,
+            short_id,
+            token: optional_id(value,"token")?,
+            data: if value.has_key("params") {
+                let mut data = Properties::from_params(&value["params"])?;
+                // 2.x 协议写入时 key 放在外层
+                if let Some(key) = value["key"].as_str() {
+                    data.set("key",key);
+                }
+                data
+            } else {
+                Properties::from_json(&value["data"])?
+            },
+            extra: extra(value,&["cmd","model","sid","short_id","token","data","params","key"]),
+        })
+    }
+
+    fn write_json(&self,value:&mut JsonValue,version:Version,object:bool){
+        if let Some(model) = &self.model {
+            value["model"] = model.as_str().into();
+        }
+        value["sid"] = self.sid.as_str().into();
+        if let Some(short_id) = self.short_id {
+            value["short_id"] = short_id.value().into();
+        }
+        if let Some(token) = &self.token {
+            value["token"] = token.as_str().into();
+        }
+        match version {
+            Version::V1 => if !self.data.is_empty() {
+                value["data"] = if object { self.data.to_json() } else { self.data.to_json().dump().into() };
+            },
+            Version::V2 => {
+                let mut params = Vec::with_capacity(self.data.len());
+                for (key,field) in self.data.iter() {
+                    if object && key == "key" {
+                        value["key"] = field.clone();
+                    } else {
+                        let mut param = JsonValue::new_object();
+                        param[key] = field.clone();
+                        params.push(param);
+                    }
+                }
+                value["params"] = JsonValue::Array(params);
+            },
+        }
+        write_extra(value,&self.extra);
+    }
+
+    ///
+    /// 应答报文中网关返回的错误, 如 `{"error":"Invalid key"}`
+    ///
+    pub fn error(&self)->Option<AckError>{
+        self.data.get_str("error").map(AckError::from)
+    }
+}
+
+impl Default for Properties{
+    fn default()->Self{
+        Self{entries:JsonValue::new_object()}
+    }
+}
+
+impl Properties{
+
+    ///
+    /// 创建空的属性表
+    ///
+    pub fn new()->Self{
+        Self::default()
+    }
+
+    ///
+    /// 解析 `data` 字段, 兼容 JSON 字符串和 JSON 对象两种形式
+    ///
+    pub fn from_json(value:&JsonValue)->Result<Self,ProtocolError>{
+        let entries = match value {
+            JsonValue::Null => JsonValue::new_object(),
+            JsonValue::Object(_) => value.clone(),
+            _ => {
+                let text = value.as_str().ok_or(ProtocolError::InvalidField("data"))?;
+                json::parse(text).map_err(|e| ProtocolError::InvalidData(e.to_string()))?
+            }
+        };
+        if !entries.is_object() {
+            return Err(ProtocolError::InvalidData(format!("expected object, found `{}`",entries.dump())));
+        }
+        Ok(Self{entries})
+    }
+
+    ///
+    /// 解析 2.x 协议的 `params` 数组, 每个元素都是单个键值对的对象
+    ///
+    pub fn from_params(value:&JsonValue)->Result<Self,ProtocolError>{
+        if !value.is_array() {
+            return Err(ProtocolError::InvalidField("params"));
+        }
+        let mut entries = JsonValue::new_object();
+        for param in value.members() {
+            if !param.is_object() {
+                return Err(ProtocolError::InvalidData(format!("expected object, found `{}`",param.dump())));
+            }
+            for (key,field) in param.entries() {
+                entries[key] = field.clone();
+            }
+        }
+        Ok(Self{entries})
+    }
+
+    ///
+    /// 转化成 JSON 对象
+    ///
+    pub fn to_json(&self)->JsonValue{
+        self.entries.clone()
+    }
+
+    ///
+    /// 写入属性
+    ///
+    pub fn set<T:Into<JsonValue>>(&mut self,key:&str,value:T){
+        self.entries[key] = value.into();
+    }
+
+    ///
+    /// 读取原始属性
+    ///
+    pub fn get(&self,key:&str)->Option<&JsonValue>{
+        match &self.entries[key] {
+            JsonValue::Null => None,
+            value => Some(value),
+        }
+    }
+
+    ///
+    /// 读取字符串属性
+    ///
+    pub fn get_str(&self,key:&str)->Option<&str>{
+        self.entries[key].as_str()
+    }
+
+    ///
+    /// 读取数字属性, 网关经常用字符串传递数字, 这里一并兼容
+    ///
+    pub fn get_i64(&self,key:&str)->Option<i64>{
+        let value = &self.entries[key];
+        value.as_i64().or_else(|| value.as_str().and_then(|s| s.trim().parse().ok()))
+    }
+
+    ///
+    /// 读取小数属性, 同样兼容字符串形式的数字
+    ///
+    pub fn get_f64(&self,key:&str)->Option<f64>{
+        let value = &self.entries[key];
+        value.as_f64().or_else(|| value.as_str().and_then(|s| s.trim().parse().ok()))
+    }
+
+    ///
+    /// 是否包含属性
+    ///
+    pub fn contains(&self,key:&str)->bool{
+        self.entries.has_key(key)
+    }
+
+    ///
+    /// 属性遍历
+    ///
+    pub fn iter(&self)->impl Iterator<Item=(&str,&JsonValue)>{
+        self.entries.entries()
+    }
+
+    pub fn len(&self)->usize{
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self)->bool{
+        self.entries.is_empty()
+    }
+
+    ///
+    /// 设备状态, 如 `open`/`close`/`motion`/`click`
+    ///
+    pub fn status(&self)->Option<&str>{
+        self.get_str("status")
+    }
+
+    ///
+    /// 读取状态类属性并转换成 `DeviceStatus`, 如 `status`/`channel_0`/`channel_1`
+    ///
+    pub fn device_status(&self,key:&str)->Option<DeviceStatus>{
+        self.get_str(key).and_then(|status| status.parse().ok())
+    }
+
+    ///
+    /// 电池电压, 单位 mV
+    ///
+    pub fn voltage(&self)->Option<u32>{
+        self.get_i64("voltage").and_then(|v| std::convert::TryFrom::try_from(v).ok())
+    }
+
+    ///
+    /// 温度, 单位 0.01 ℃
+    ///
+    pub fn temperature(&self)->Option<i32>{
+        self.get_i64("temperature").and_then(|v| std::convert::TryFrom::try_from(v).ok())
+    }
+
+    ///
+    /// 湿度, 单位 0.01 %RH
+    ///
+    pub fn humidity(&self)->Option<i32>{
+        self.get_i64("humidity").and_then(|v| std::convert::TryFrom::try_from(v).ok())
+    }
+
+    ///
+    /// 气压, 单位 Pa
+    ///
+    pub fn pressure(&self)->Option<i32>{
+        self.get_i64("pressure").and_then(|v| std::convert::TryFrom::try_from(v).ok())
+    }
+
+    ///
+    /// 网关心跳上报的 IP 地址
+    ///
+    pub fn ip(&self)->Option<&str>{
+        self.get_str("ip")
+    }
+}
+
+
+///
+/// 写入命令构建器
+///
+/// 写入设备需要在 `data` 中附带 `key`, 而 `key` 是网关密码对最新心跳 token 加密之后的结果:
+/// ```
+/// use aqara_rs::protocol::{WriteCommand, Message};
+/// let command = WriteCommand::new("158d0001a2b3c4".parse().unwrap(),"plug".parse().unwrap()).set("status","on");
+/// let message = command.sign("0987654321qwerty",&"1234567890abcdef".parse().unwrap()).unwrap();
+/// if let Message::Write(frame) = message {
+///     assert_eq!(frame.data.get_str("key"),Some("3EB43E37C20AFF4C5872CC0D04D81314"));
+/// }
+/// ```
+///
+/// 一般不需要手动签名, 通过 `device::Gateway::write` 推送时会自动获取对应网关的 token
+///
+#[derive(Debug,Clone,PartialEq)]
+pub struct WriteCommand{
+    sid:Sid,
+    model:Model,
+    data:Properties,
+    retry:bool,
+}
+
+impl WriteCommand{
+
+    pub fn new(sid:Sid,model:Model)->Self{
+        Self{
+            sid,
+            model,
+            data: Properties::new(),
+            retry: false,
+        }
+    }
+
+    ///
+    /// 网关应答 `Invalid key` 时是否在下一次心跳之后重试一次
+    ///
+    pub fn retry(mut self,retry:bool)->Self{
+        self.retry = retry;
+        self
+    }
+
+    pub fn retry_enabled(&self)->bool{
+        self.retry
+    }
+
+    ///
+    /// 追加写入的属性
+    ///
+    pub fn set<T:Into<JsonValue>>(mut self,key:&str,value:T)->Self{
+        self.data.set(key,value);
+        self
+    }
+
+    ///
+    /// 写入的目标设备
+    ///
+    pub fn sid(&self)->&Sid{
+        &self.sid
+    }
+
+    pub fn model(&self)->&Model{
+        &self.model
+    }
+
+    ///
+    /// 写入的属性
+    ///
+    pub fn data(&self)->&Properties{
+        &self.data
+    }
+
+    ///
+    /// 使用网关密码和 token 生成签名之后的 `write` 报文
+    ///
+    pub fn sign(&self,password:&str,token:&Token)->Result<Message,crypto::symmetriccipher::SymmetricCipherError>{
+        let key = KeyBuilder::sign(password,token)?;
+        let mut data = self.data.clone();
+        data.set("key",key);
+        Ok(Message::Write(Frame{
+            model: Some(self.model.clone()),
+            sid: self.sid.clone(),
+            short_id: None,
+            token: None,
+            data,
+            extra: JsonValue::new_object(),
+        }))
+    }
+}
+
+
+///
+/// 收集未识别的字段
+///
+fn extra(value:&JsonValue,known:&[&str])->JsonValue{
+    let mut extra = JsonValue::new_object();
+    for (key,field) in value.entries() {
+        if !known.contains(&key) {
+            extra[key] = field.clone();
+        }
+    }
+    extra
+}
+
+///
+/// 写回未识别的字段, 不覆盖已有字段
+///
+fn write_extra(value:&mut JsonValue,extra:&JsonValue){
+    for (key,field) in extra.entries() {
+        if !value.has_key(key) {
+            value[key] = field.clone();
+        }
+    }
+}
+
+///
+/// 获取必须的字符串字段
+///
+fn required_str(value:&JsonValue,name:&'static str)->Result<String,ProtocolError>{
+    match &value[name] {
+        JsonValue::Null => Err(ProtocolError::MissingField(name)),
+        field => field.as_str().map(String::from).ok_or(ProtocolError::InvalidField(name)),
+    }
+}
+
+///
+/// 获取必须的标识字段, 如 sid/model/token
+///
+fn required_id<T:FromStr>(value:&JsonValue,name:&'static str)->Result<T,ProtocolError>{
+    required_str(value,name)?.parse().map_err(|_| ProtocolError::InvalidField(name))
+}
+
+fn optional_id<T:FromStr>(value:&JsonValue,name:&'static str)->Result<Option<T>,ProtocolError>{
+    match &value[name] {
+        JsonValue::Null => Ok(None),
+        _ => required_id(value,name).map(Some),
+    }
+}
+
+///
+/// 获取必须的数字字段, 兼容字符串形式的数字
+///
+fn required_u32(value:&JsonValue,name:&'static str)->Result<u32,ProtocolError>{
+    match &value[name] {
+        JsonValue::Null => Err(ProtocolError::MissingField(name)),
+        field => as_u32(field).ok_or(ProtocolError::InvalidField(name)),
+    }
+}
+
+fn as_u32(value:&JsonValue)->Option<u32>{
+    value.as_u32().or_else(|| value.as_str().and_then(|s| s.parse().ok()))
+}
+
+///
+/// 子设备列表, 网关发出的是序列化之后的 JSON 数组字符串
+///
+fn id_list(value:&JsonValue)->Result<Vec<Sid>,ProtocolError>{
+    let parsed;
+    let array = match value.as_str() {
+        Some(text) => {
+            parsed = json::parse(text).map_err(|_| ProtocolError::InvalidField("data"))?;
+            &parsed
+        },
+        None => value,
+    };
+    if !array.is_array() {
+        return Err(ProtocolError::InvalidField("data"));
+    }
+    array.members()
+        .map(|sid| sid.as_str().and_then(|sid| sid.parse().ok()).ok_or(ProtocolError::InvalidField("data")))
+        .collect()
+}
+
+///
+/// 2.x 协议的子设备列表, 每个元素包含 sid 和 model
+///
+fn dev_list(value:&JsonValue)->Result<Vec<Sid>,ProtocolError>{
+    if !value.is_array() {
+        return Err(ProtocolError::InvalidField("dev_list"));
+    }
+    value.members()
+        .map(|dev| dev["sid"].as_str().and_then(|sid| sid.parse().ok()).ok_or(ProtocolError::InvalidField("dev_list")))
+        .collect()
+}
@@ -361,8 +361,7 @@ impl Drop for Multicast{
     /// 析构方法, 退出的时候需要离开分组
     ///
     fn drop(&mut self) {
-        if self.multicast.is_some() {
-            let target = self.multicast.unwrap();
+        if let Some(target) = self.multicast {
             if let IpAddr::V4(address) = target.ip(){
                 let _ = self.ss.leave_multicast_v4(
                     &address,
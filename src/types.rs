@@ -0,0 +1,214 @@
+//!
+//! # 协议标识类型
+//!
+//! 设备 sid, short_id, 型号和心跳 token 在报文里都是字符串, 这里包装成校验过的类型,
+//! 可以直接作为 `HashMap` 的键使用, 非法的 token 也会在进入 AES 加密之前被拦截
+//!
+//! 启用 `serde` 特性之后这些类型支持序列化, 序列化结果和报文中的形式一致
+//!
+
+use crate::prelude::AES_KEY_SIZE;
+use std::str::FromStr;
+
+///
+/// 标识解析错误
+///
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct ParseIdError{
+    kind:&'static str,
+    value:String,
+}
+
+impl ParseIdError{
+    fn new(kind:&'static str,value:&str)->Self{
+        Self{kind,value:value.to_string()}
+    }
+}
+
+impl std::fmt::Display for ParseIdError{
+    fn fmt(&self,f:&mut std::fmt::Formatter<'_>)->std::fmt::Result{
+        write!(f,"invalid {} `{}`",self.kind,self.value)
+    }
+}
+
+impl std::error::Error for ParseIdError{}
+
+
+///
+/// 设备唯一标识
+///
+/// 网关为 12 位 MAC 地址, 子设备为 ZigBee IEEE 地址( 16 位, 网关通常省略前导 `00` 只发送 14 位 ),
+/// 2.x 协议会带上 `lumi.` 前缀. 统一保存为小写
+///
+/// ```
+/// use aqara_rs::types::Sid;
+/// let sid:Sid = "158D0001A2B3C4".parse().unwrap();
+/// assert_eq!(sid.to_string(),"158d0001a2b3c4");
+/// assert!("158d0001zz".parse::<Sid>().is_err());
+/// ```
+///
+#[derive(Debug,Clone,PartialEq,Eq,Hash,PartialOrd,Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "String", into = "String"))]
+pub struct Sid(String);
+
+impl Sid{
+    pub fn as_str(&self)->&str{
+        self.0.as_str()
+    }
+}
+
+impl FromStr for Sid{
+    type Err = ParseIdError;
+
+    fn from_str(s:&str)->Result<Self,Self::Err>{
+        let lower = s.trim().to_ascii_lowercase();
+        let hex = lower.strip_prefix("lumi.").unwrap_or(&lower);
+        if (12..=16).contains(&hex.len()) && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            Ok(Sid(lower))
+        } else {
+            Err(ParseIdError::new("sid",s))
+        }
+    }
+}
+
+
+///
+/// 设备在 ZigBee 网络中的短地址, 网关自身为 `0`
+///
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash,PartialOrd,Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ShortId(pub u16);
+
+impl ShortId{
+    pub fn value(&self)->u16{
+        self.0
+    }
+}
+
+impl FromStr for ShortId{
+    type Err = ParseIdError;
+
+    fn from_str(s:&str)->Result<Self,Self::Err>{
+        s.trim().parse().map(ShortId).map_err(|_| ParseIdError::new("short_id",s))
+    }
+}
+
+
+///
+/// 设备型号, 如 `gateway`, `sensor_ht`, `magnet`, `weather.v1`
+///
+#[derive(Debug,Clone,PartialEq,Eq,Hash,PartialOrd,Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "String", into = "String"))]
+pub struct Model(String);
+
+impl Model{
+    pub fn as_str(&self)->&str{
+        self.0.as_str()
+    }
+
+    ///
+    /// 是否为网关自身( `gateway`, `gateway.v3`, `gateway.aq1`, `acpartner.v3` 等 )
+    ///
+    pub fn is_gateway(&self)->bool{
+        self.0.starts_with("gateway") || self.0.starts_with("acpartner")
+    }
+}
+
+impl FromStr for Model{
+    type Err = ParseIdError;
+
+    fn from_str(s:&str)->Result<Self,Self::Err>{
+        let model = s.trim();
+        if !model.is_empty() && model.chars().all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '_' || c == '-') {
+            Ok(Model(model.to_string()))
+        } else {
+            Err(ParseIdError::new("model",s))
+        }
+    }
+}
+
+
+///
+/// 网关心跳下发的 token, 固定 16 字节 ASCII 字符串
+///
+/// ```
+/// use aqara_rs::types::Token;
+/// assert!("1234567890abcdef".parse::<Token>().is_ok());
+/// assert!("1234".parse::<Token>().is_err());
+/// ```
+///
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "String", into = "String"))]
+pub struct Token([u8;AES_KEY_SIZE]);
+
+impl Token{
+    pub fn as_bytes(&self)->&[u8]{
+        &self.0
+    }
+
+    pub fn as_str(&self)->&str{
+        // 构建时已经校验为 ASCII
+        std::str::from_utf8(&self.0).unwrap_or_default()
+    }
+}
+
+impl FromStr for Token{
+    type Err = ParseIdError;
+
+    fn from_str(s:&str)->Result<Self,Self::Err>{
+        if s.len() != AES_KEY_SIZE || !s.is_ascii() {
+            return Err(ParseIdError::new("token",s));
+        }
+        let mut token = [0;AES_KEY_SIZE];
+        token.copy_from_slice(s.as_bytes());
+        Ok(Token(token))
+    }
+}
+
+
+macro_rules! string_id {
+    ($($name:ident),*) => {$(
+        impl std::fmt::Display for $name{
+            fn fmt(&self,f:&mut std::fmt::Formatter<'_>)->std::fmt::Result{
+                f.write_str(self.as_str())
+            }
+        }
+
+        impl std::convert::TryFrom<String> for $name{
+            type Error = ParseIdError;
+
+            fn try_from(value:String)->Result<Self,Self::Error>{
+                value.parse()
+            }
+        }
+
+        impl From<$name> for String{
+            fn from(value:$name)->Self{
+                value.as_str().to_string()
+            }
+        }
+
+        impl PartialEq<str> for $name{
+            fn eq(&self,other:&str)->bool{
+                self.as_str() == other
+            }
+        }
+
+        impl PartialEq<&str> for $name{
+            fn eq(&self,other:&&str)->bool{
+                self.as_str() == *other
+            }
+        }
+    )*};
+}
+
+string_id!(Sid,Model,Token);
+
+impl std::fmt::Display for ShortId{
+    fn fmt(&self,f:&mut std::fmt::Formatter<'_>)->std::fmt::Result{
+        write!(f,"{}",self.0)
+    }
+}
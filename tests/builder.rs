@@ -12,3 +12,9 @@ fn key_works()->Result<(),Box<crypto::symmetriccipher::SymmetricCipherError>>{
 
 
 
+#[test]
+fn key_matches_reference()->Result<(),Box<crypto::symmetriccipher::SymmetricCipherError>>{
+    let msg = KeyBuilder::encode_str("0987654321qwerty","1234567890abcdef")?;
+    assert_eq!(msg,"3EB43E37C20AFF4C5872CC0D04D81314");
+    Ok(())
+}
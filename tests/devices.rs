@@ -0,0 +1,341 @@
+use aqara_rs::protocol::{AckError, Frame, Message};
+use aqara_rs::devices::{Alarm, Battery, BatteryCurve, BatteryEvent, Button, ButtonEvent, Channel, Climate, Cube, CubeEvent, Curtain, CurtainEvent, Device, Door, DoorEvent, Gesture, Hub, HubEvent, Light, Lock, LockEvent, Tone, UnlockMethod, UserTone, Motion, MotionEvent, Plug, PlugEvent, Safety, SafetyEvent, Sensitivity, Vibration, VibrationEvent, WallSwitch, WallSwitchEvent};
+use std::time::{Duration, Instant};
+
+fn frame(raw:&str)->Frame{
+    match Message::parse(raw.as_bytes()).unwrap() {
+        Message::Report(frame)
+        | Message::ReadAck(frame)
+        | Message::WriteAck(frame)
+        | Message::Heartbeat(frame) => frame,
+        other => panic!("unexpected {:?}",other),
+    }
+}
+
+#[test]
+fn climate_works(){
+    let ht = frame(r#"{"cmd":"heartbeat","model":"sensor_ht","sid":"158d0001a2b3c4","short_id":20712,"data":"{\"voltage\":3015,\"temperature\":\"-250\",\"humidity\":\"5206\"}"}"#);
+    let climate = Climate::from_frame(&ht).unwrap();
+    assert_eq!(climate.model,"sensor_ht");
+    assert_eq!(climate.temperature,Some(-2.5));
+    assert_eq!(climate.humidity,Some(52.06));
+    assert_eq!(climate.pressure,None);
+
+    let magnet = frame(r#"{"cmd":"report","model":"magnet","sid":"158d0001a2b3c5","data":"{\"temperature\":\"2350\"}"}"#);
+    assert!(Climate::from_frame(&magnet).is_none());
+
+    let broken = frame(r#"{"cmd":"report","model":"sensor_ht","sid":"158d0001a2b3c4","data":"{\"temperature\":\"10000\",\"humidity\":\"10000\"}"}"#);
+    assert!(Climate::from_frame(&broken).is_none());
+}
+
+#[test]
+fn door_works(){
+    let mut door = Door::new("158d0001a2b3c4".parse().unwrap());
+    assert_eq!(door.is_open(),None);
+
+    let open = frame(r#"{"cmd":"report","model":"magnet","sid":"158d0001a2b3c4","data":"{\"status\":\"open\"}"}"#);
+    assert_eq!(door.update(&open),vec![DoorEvent::Opened]);
+    // 心跳重复上报相同状态不产生事件
+    let heartbeat = frame(r#"{"cmd":"heartbeat","model":"magnet","sid":"158d0001a2b3c4","data":"{\"voltage\":3025,\"status\":\"open\"}"}"#);
+    assert!(door.update(&heartbeat).is_empty());
+
+    let left = frame(r#"{"cmd":"report","model":"magnet","sid":"158d0001a2b3c4","data":"{\"no_close\":\"120\"}"}"#);
+    assert_eq!(door.update(&left),vec![DoorEvent::LeftOpen{seconds:120}]);
+
+    let close = frame(r#"{"cmd":"report","model":"magnet","sid":"158d0001a2b3c4","data":"{\"status\":\"close\"}"}"#);
+    assert_eq!(door.update(&close),vec![DoorEvent::Closed]);
+    assert_eq!(door.is_open(),Some(false));
+
+    let other = frame(r#"{"cmd":"report","model":"magnet","sid":"158d0001a2b3c5","data":"{\"status\":\"open\"}"}"#);
+    assert!(door.update(&other).is_empty());
+}
+
+#[test]
+fn motion_works(){
+    let mut motion = Motion::new("158d0001a2b3c4".parse().unwrap());
+    let start = Instant::now();
+
+    let detected = frame(r#"{"cmd":"report","model":"motion","sid":"158d0001a2b3c4","data":"{\"status\":\"motion\"}"}"#);
+    assert_eq!(motion.update_at(&detected,start),vec![MotionEvent::Detected]);
+
+    let vacant = frame(r#"{"cmd":"report","model":"motion","sid":"158d0001a2b3c4","data":"{\"no_motion\":\"120\"}"}"#);
+    let later = start + Duration::from_secs(180);
+    assert_eq!(motion.update_at(&vacant,later),vec![MotionEvent::Vacant{seconds:120}]);
+    assert_eq!(motion.is_occupied(),Some(false));
+    assert_eq!(motion.since_last_motion(later),Some(Duration::from_secs(180)));
+    assert_eq!(motion.lux(),None);
+}
+
+#[test]
+fn button_works(){
+    let mut button = Button::new("158d0001a2b3c4".parse().unwrap());
+    let start = Instant::now();
+
+    let press = frame(r#"{"cmd":"report","model":"switch","sid":"158d0001a2b3c4","data":"{\"status\":\"long_click_press\"}"}"#);
+    assert_eq!(button.update_at(&press,start),vec![ButtonEvent{channel:Channel::Channel0,gesture:Gesture::Press}]);
+    assert!(button.is_pressed(Channel::Channel0));
+
+    let release = frame(r#"{"cmd":"report","model":"switch","sid":"158d0001a2b3c4","data":"{\"status\":\"long_click_release\"}"}"#);
+    let held = Some(Duration::from_millis(2500));
+    assert_eq!(
+        button.update_at(&release,start + Duration::from_millis(2500)),
+        vec![ButtonEvent{channel:Channel::Channel0,gesture:Gesture::Release{held}}]
+    );
+    assert!(!button.is_pressed(Channel::Channel0));
+
+    let right = frame(r#"{"cmd":"report","model":"86sw2","sid":"158d0001a2b3c4","data":"{\"channel_1\":\"double_click\"}"}"#);
+    assert_eq!(button.update(&right),vec![ButtonEvent{channel:Channel::Channel1,gesture:Gesture::DoubleClick}]);
+}
+
+#[test]
+fn cube_works(){
+    let mut cube = Cube::new("158d0001a2b3c4".parse().unwrap());
+
+    let flip = frame(r#"{"cmd":"report","model":"cube","sid":"158d0001a2b3c4","data":"{\"status\":\"flip90\"}"}"#);
+    assert_eq!(cube.update(&flip),vec![CubeEvent::Flip90]);
+
+    let rotate = frame(r#"{"cmd":"report","model":"cube","sid":"158d0001a2b3c4","data":"{\"rotate\":\"45\"}"}"#);
+    assert_eq!(cube.update(&rotate),vec![CubeEvent::Rotate{degrees:45,duration:None}]);
+
+    let timed = frame(r#"{"cmd":"report","model":"cube","sid":"158d0001a2b3c4","data":"{\"rotate\":\"-90,1200\"}"}"#);
+    assert_eq!(cube.update(&timed),vec![CubeEvent::Rotate{degrees:-90,duration:Some(Duration::from_millis(1200))}]);
+    assert_eq!(cube.last_event(),Some(CubeEvent::Rotate{degrees:-90,duration:Some(Duration::from_millis(1200))}));
+
+    let other = frame(r#"{"cmd":"report","model":"magnet","sid":"158d0001a2b3c4","data":"{\"status\":\"open\"}"}"#);
+    assert!(cube.update(&other).is_empty());
+}
+
+#[test]
+fn plug_works(){
+    let mut plug = Plug::new("158d0001a2b3c4".parse().unwrap(),"ctrl_86plug.aq1".parse().unwrap());
+
+    let report = frame(r#"{"cmd":"report","model":"ctrl_86plug.aq1","sid":"158d0001a2b3c4","data":"{\"status\":\"on\",\"inuse\":\"1\",\"power_consumed\":\"1240\"}"}"#);
+    assert_eq!(plug.update(&report),vec![
+        PlugEvent::Switched{on:true},
+        PlugEvent::InUse{inuse:true},
+        PlugEvent::PowerConsumed{wh:1240},
+    ]);
+
+    let command = plug.toggle();
+    assert_eq!(command.data().get_str("status"),Some("off"));
+    assert!(command.retry_enabled());
+    assert_eq!(plug.pending(),Some(false));
+
+    let ack = frame(r#"{"cmd":"write_ack","model":"ctrl_86plug.aq1","sid":"158d0001a2b3c4","data":"{\"status\":\"off\",\"inuse\":\"1\"}"}"#);
+    assert_eq!(plug.update(&ack),vec![PlugEvent::Switched{on:false}]);
+    assert_eq!(plug.is_on(),Some(false));
+    assert_eq!(plug.pending(),None);
+
+    // 写入失败之后撤销等待确认的状态, 再次切换按照实际状态处理
+    plug.turn_on();
+    let error = frame(r#"{"cmd":"write_ack","model":"ctrl_86plug.aq1","sid":"158d0001a2b3c4","data":"{\"error\":\"Invalid key\"}"}"#);
+    assert_eq!(plug.update(&error),vec![PlugEvent::Rejected(AckError::InvalidKey)]);
+    assert_eq!(plug.pending(),None);
+    assert_eq!(plug.toggle().data().get_str("status"),Some("on"));
+}
+
+#[test]
+fn wall_switch_works(){
+    let mut single = WallSwitch::new("158d0001a2b3c4".parse().unwrap(),"ctrl_neutral1".parse().unwrap());
+    assert_eq!(single.channels(),1);
+    let command = single.turn_on(Channel::Both).unwrap();
+    assert_eq!(command.data().get_str("channel_0"),Some("on"));
+    assert!(!command.data().contains("channel_1"));
+    assert!(single.turn_on(Channel::Channel1).is_none());
+    assert!(single.toggle(Channel::Channel1).is_none());
+    assert_eq!(single.is_on(Channel::Channel1),None);
+    assert_eq!(single.pending(Channel::Channel1),None);
+
+    let mut aq1 = WallSwitch::new("158d0001a2b3c4".parse().unwrap(),"ctrl_ln2.aq1".parse().unwrap());
+    assert_eq!(aq1.channels(),2);
+    let command = aq1.turn_off(Channel::Both).unwrap();
+    assert_eq!(command.data().get_str("channel_0"),Some("off"));
+    assert_eq!(command.data().get_str("channel_1"),Some("off"));
+    assert_eq!(WallSwitch::new("158d0001a2b3c4".parse().unwrap(),"ctrl_neutral1.aq1".parse().unwrap()).channels(),1);
+
+    let mut switch = WallSwitch::new("158d0001a2b3c4".parse().unwrap(),"ctrl_ln2".parse().unwrap());
+    assert!(switch.is_live_neutral());
+    let report = frame(r#"{"cmd":"report","model":"ctrl_ln2","sid":"158d0001a2b3c4","data":"{\"channel_0\":\"on\",\"channel_1\":\"off\",\"load_power\":\"11.5\"}"}"#);
+    assert_eq!(switch.update(&report),vec![
+        WallSwitchEvent::Switched{channel:Channel::Channel0,on:true},
+        WallSwitchEvent::Switched{channel:Channel::Channel1,on:false},
+        WallSwitchEvent::LoadPower{watts:11.5},
+    ]);
+    assert_eq!(switch.is_on(Channel::Both),Some(false));
+
+    let command = switch.toggle(Channel::Channel1).unwrap();
+    assert_eq!(command.data().get_str("channel_1"),Some("on"));
+    assert!(!command.data().contains("channel_0"));
+    assert_eq!(switch.pending(Channel::Channel1),Some(true));
+
+    let ack = frame(r#"{"cmd":"write_ack","model":"ctrl_ln2","sid":"158d0001a2b3c4","data":"{\"channel_0\":\"on\",\"channel_1\":\"on\"}"}"#);
+    assert_eq!(switch.update(&ack),vec![WallSwitchEvent::Switched{channel:Channel::Channel1,on:true}]);
+    assert_eq!(switch.pending(Channel::Channel1),None);
+    assert_eq!(switch.is_on(Channel::Both),Some(true));
+
+    switch.turn_off(Channel::Both).unwrap();
+    let error = frame(r#"{"cmd":"write_ack","model":"ctrl_ln2","sid":"158d0001a2b3c4","data":"{\"error\":\"Invalid device\"}"}"#);
+    assert_eq!(switch.update(&error),vec![WallSwitchEvent::Rejected(AckError::InvalidDevice)]);
+    assert_eq!(switch.pending(Channel::Channel0),None);
+    assert_eq!(switch.toggle(Channel::Channel0).unwrap().data().get_str("channel_0"),Some("off"));
+}
+
+#[test]
+fn safety_works(){
+    let mut gas = Safety::new("158d0001a2b3c4".parse().unwrap(),"natgas".parse().unwrap());
+    let report = frame(r#"{"cmd":"report","model":"natgas","sid":"158d0001a2b3c4","data":"{\"alarm\":\"64\"}"}"#);
+    assert_eq!(gas.update(&report),vec![SafetyEvent::Alarm(Alarm::SensitivityFault)]);
+    assert!(gas.alarm().unwrap().is_fault());
+    assert_eq!(Alarm::from_code(32768),Alarm::I2cFault);
+    assert_eq!(Alarm::from_code(4),Alarm::Unknown(4));
+
+    let command = gas.set_sensitivity(Sensitivity::High).unwrap();
+    assert_eq!(command.data().get_i64("sensitivity"),Some(3));
+    assert_eq!(gas.self_test().unwrap().data().get_i64("selftest"),Some(1));
+
+    let mut leak = Safety::new("158d0001a2b3c5".parse().unwrap(),"sensor_wleak.aq1".parse().unwrap());
+    assert!(leak.set_sensitivity(Sensitivity::Low).is_none());
+    let report = frame(r#"{"cmd":"report","model":"sensor_wleak.aq1","sid":"158d0001a2b3c5","data":"{\"status\":\"leak\"}"}"#);
+    assert_eq!(leak.update(&report),vec![SafetyEvent::Leak{leak:true}]);
+    assert_eq!(leak.is_leaking(),Some(true));
+}
+
+#[test]
+fn vibration_works(){
+    let mut vibration = Vibration::new("158d0001a2b3c4".parse().unwrap(),"vibration".parse().unwrap());
+
+    let tilt = frame(r#"{"cmd":"report","model":"vibration","sid":"158d0001a2b3c4","data":"{\"status\":\"tilt\",\"final_tilt_angle\":\"27\"}"}"#);
+    assert_eq!(vibration.update(&tilt),vec![VibrationEvent::Tilt,VibrationEvent::TiltAngle{degrees:27}]);
+    assert_eq!(vibration.tilt_angle(),Some(27));
+
+    let activity = frame(r#"{"cmd":"report","model":"vibration","sid":"158d0001a2b3c4","data":"{\"bed_activity\":\"42\",\"coordination\":\"1,2\"}"}"#);
+    assert_eq!(vibration.update(&activity),vec![VibrationEvent::BedActivity{value:42}]);
+    assert_eq!(vibration.coordination(),None);
+
+    let command = vibration.set_sensitivity(Sensitivity::Medium);
+    assert_eq!(command.data().get_i64("sensitivity"),Some(2));
+}
+
+#[test]
+fn curtain_works(){
+    let mut curtain = Curtain::new("158d0001a2b3c4".parse().unwrap(),"curtain".parse().unwrap());
+
+    let command = curtain.open();
+    assert_eq!(command.data().get_str("curtain_status"),Some("open"));
+    assert_eq!(curtain.target(),Some(100));
+
+    let moving = frame(r#"{"cmd":"report","model":"curtain","sid":"158d0001a2b3c4","data":"{\"curtain_status\":\"open\",\"curtain_level\":\"35\"}"}"#);
+    assert_eq!(curtain.update(&moving),vec![CurtainEvent::Opening,CurtainEvent::Position{percent:35}]);
+    assert_eq!(curtain.target(),Some(100));
+
+    let stopped = frame(r#"{"cmd":"report","model":"curtain","sid":"158d0001a2b3c4","data":"{\"curtain_status\":\"stop\",\"curtain_level\":\"120\"}"}"#);
+    assert_eq!(curtain.update(&stopped),vec![CurtainEvent::Stopped]);
+    assert_eq!(curtain.target(),None);
+    assert_eq!(curtain.position(),Some(35));
+
+    assert_eq!(curtain.set_position(150).data().get_i64("curtain_level"),Some(100));
+}
+
+#[test]
+fn hub_works(){
+    assert_eq!(Light::from_rgb(0),Light::default());
+    assert_eq!(Light::new(200,1,2,3).to_rgb(),0x64010203);
+
+    let mut hub = Hub::new("34ce00112233".parse().unwrap(),"gateway".parse().unwrap());
+    assert_eq!(hub.set_color(0,0,255).data().get_i64("rgb"),Some(0x640000ff));
+
+    let heartbeat = frame(r#"{"cmd":"heartbeat","model":"gateway","sid":"34ce00112233","short_id":"0","token":"1234567890abcdef","data":"{\"ip\":\"192.168.1.2\",\"rgb\":\"838926080\"}"}"#);
+    assert_eq!(hub.update(&heartbeat),vec![HubEvent::Light(Light::new(50,0,255,0))]);
+    assert_eq!(hub.set_color(255,0,0).data().get_i64("rgb"),Some(0x32ff0000));
+    assert_eq!(hub.turn_off_light().data().get_i64("rgb"),Some(0));
+}
+
+#[test]
+fn tone_works(){
+    for tone in Tone::BUILTIN.iter() {
+        assert_eq!(Tone::from_mid(tone.mid()),Some(*tone));
+        assert_eq!(tone.name().parse::<Tone>(),Ok(*tone));
+    }
+    assert_eq!(Tone::from_mid(10000),None);
+    assert!("siren".parse::<Tone>().is_err());
+    let user = Tone::from_mid(10002).unwrap();
+    assert_eq!(user.to_string().parse::<Tone>(),Ok(user));
+    assert!("user(10000)".parse::<Tone>().is_err());
+    assert!("user(abc)".parse::<Tone>().is_err());
+
+    let hub = Hub::new("34ce00112233".parse().unwrap(),"gateway".parse().unwrap());
+    let command = hub.play(Tone::AirRaid,120);
+    assert_eq!(command.data().get_i64("mid"),Some(7));
+    assert_eq!(command.data().get_i64("vol"),Some(100));
+    assert_eq!(hub.play(Tone::from_mid(10002).unwrap(),30).data().get_i64("mid"),Some(10002));
+    assert!(UserTone::new(10000).is_none());
+    assert!(UserTone::new(5).is_none());
+    assert_eq!(hub.stop_sound().data().get_i64("mid"),Some(10000));
+    assert!(!hub.stop_sound().data().contains("vol"));
+}
+
+#[test]
+fn lock_works(){
+    let mut lock = Lock::new("158d0001a2b3c4".parse().unwrap());
+    let wrong = frame(r#"{"cmd":"report","model":"lock.aq1","sid":"158d0001a2b3c4","data":"{\"verified_wrong\":\"1\"}"}"#);
+    assert_eq!(lock.update(&wrong),vec![LockEvent::Rejected{attempts:1}]);
+    let again = frame(r#"{"cmd":"report","model":"lock.aq1","sid":"158d0001a2b3c4","data":"{\"verified_wrong\":\"3\"}"}"#);
+    assert_eq!(lock.update(&again),vec![LockEvent::Rejected{attempts:3}]);
+    // 重复的上报不会重复计数
+    assert_eq!(lock.update(&again),vec![LockEvent::Rejected{attempts:3}]);
+    assert_eq!(lock.failed_attempts(),3);
+
+    let negative = frame(r#"{"cmd":"report","model":"lock.aq1","sid":"158d0001a2b3c4","data":"{\"fing_verified\":\"-1\"}"}"#);
+    assert!(lock.update(&negative).is_empty());
+
+    let card = frame(r#"{"cmd":"report","model":"lock.aq1","sid":"158d0001a2b3c4","data":"{\"card_verified\":\"196609\"}"}"#);
+    assert_eq!(lock.update(&card),vec![LockEvent::Unlocked{method:UnlockMethod::Card,user:196609}]);
+    assert_eq!(lock.last_method(),Some(UnlockMethod::Card));
+    assert_eq!(lock.failed_attempts(),0);
+    assert_eq!(lock.total_failed(),3);
+
+    // 门锁在其他地方清零之后重新计数
+    assert_eq!(lock.update(&again),vec![LockEvent::Rejected{attempts:3}]);
+    assert_eq!(lock.update(&wrong),vec![LockEvent::Rejected{attempts:1}]);
+    assert_eq!(lock.failed_attempts(),1);
+    assert_eq!(lock.total_failed(),7);
+}
+
+#[test]
+fn pairing_works(){
+    let hub = Hub::new("34ce00112233".parse().unwrap(),"gateway".parse().unwrap());
+    assert_eq!(hub.permit_join().data().get_str("join_permission"),Some("yes"));
+    let command = hub.remove_device(&"158d0001a2b3c4".parse().unwrap());
+    assert_eq!(command.sid(),"34ce00112233");
+    assert_eq!(command.data().get_str("remove_device"),Some("158d0001a2b3c4"));
+}
+
+#[test]
+fn battery_works(){
+    assert_eq!(BatteryCurve::for_model(&"sensor_ht".parse().unwrap()),BatteryCurve::Coin);
+    assert_eq!(BatteryCurve::for_model(&"smoke".parse().unwrap()),BatteryCurve::Linear);
+    assert_eq!(BatteryCurve::Linear.percentage(3400),100);
+    assert_eq!(BatteryCurve::Linear.percentage(2700),0);
+
+    let mut battery = Battery::new(30);
+    let sid:aqara_rs::types::Sid = "158d0001a2b3c4".parse().unwrap();
+
+    let full = frame(r#"{"cmd":"heartbeat","model":"magnet","sid":"158d0001a2b3c4","data":"{\"voltage\":3025,\"status\":\"close\"}"}"#);
+    assert_eq!(battery.update(&full),vec![BatteryEvent::Level{sid:sid.clone(),voltage:3025,percent:100}]);
+
+    let low = frame(r#"{"cmd":"heartbeat","model":"magnet","sid":"158d0001a2b3c4","data":"{\"voltage\":2800}"}"#);
+    assert_eq!(battery.update(&low),vec![
+        BatteryEvent::Level{sid:sid.clone(),voltage:2800,percent:27},
+        BatteryEvent::Low{sid:sid.clone(),percent:27},
+    ]);
+    // 电压抖动不会重复提醒
+    let jitter = frame(r#"{"cmd":"read_ack","sid":"158d0001a2b3c4","data":"{\"voltage\":2820}"}"#);
+    assert_eq!(battery.update(&jitter),vec![BatteryEvent::Level{sid:sid.clone(),voltage:2820,percent:30}]);
+    assert_eq!(battery.update(&low),vec![BatteryEvent::Level{sid:sid.clone(),voltage:2800,percent:27}]);
+    assert_eq!(battery.low(),vec![&sid]);
+
+    // 更换电池之后重新提醒
+    battery.update(&full);
+    assert!(!battery.level(&sid).unwrap().low);
+    assert_eq!(battery.update(&low).len(),2);
+}
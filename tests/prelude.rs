@@ -0,0 +1,17 @@
+use aqara_rs::prelude::DeviceStatus;
+
+#[test]
+fn status_round_trip(){
+    let wire = [
+        "open","close","motion","no_motion","click","double_click","both_click","long_click",
+        "long_click_press","long_click_release","long_both_click","on","off","stop","auto",
+        "leak","no_leak","shake","shake_air","flip90","flip180","move","tap_twice","swing",
+        "alert","free_fall","tilt","vibrate","iam",
+    ];
+    for status in wire.iter() {
+        let parsed:DeviceStatus = status.parse().unwrap();
+        assert_ne!(parsed,DeviceStatus::Unknown(status.to_string()));
+        assert_eq!(parsed.to_string(),*status);
+    }
+    assert_eq!("rotate_left".parse::<DeviceStatus>().unwrap().to_string(),"rotate_left");
+}
@@ -0,0 +1,146 @@
+use aqara_rs::protocol::{AckError, Message, ProtocolError, Version, WriteCommand};
+
+#[test]
+fn parse_iam(){
+    let msg = Message::parse(b"{\"cmd\":\"iam\",\"port\":\"9898\",\"sid\":\"f0b429cc1234\",\"model\":\"gateway\",\"proto_version\":\"1.1.2\",\"ip\":\"192.168.0.42\"}").unwrap();
+    match msg {
+        Message::Iam(iam) => {
+            assert_eq!(iam.ip,"192.168.0.42");
+            assert_eq!(iam.port,9898);
+            assert_eq!(iam.proto_version.as_deref(),Some("1.1.2"));
+        }
+        other => panic!("unexpected {:?}",other),
+    }
+}
+
+#[test]
+fn parse_id_list_ack(){
+    let msg = Message::parse(b"{\"cmd\":\"get_id_list_ack\",\"sid\":\"f0b429cc1234\",\"token\":\"1234567890abcdef\",\"data\":\"[\\\"158d0001a2b3c4\\\",\\\"158d0001a2b3c5\\\"]\"}").unwrap();
+    match msg {
+        Message::GetIdListAck(list) => assert_eq!(list.devices,vec!["158d0001a2b3c4","158d0001a2b3c5"]),
+        other => panic!("unexpected {:?}",other),
+    }
+}
+
+#[test]
+fn round_trip(){
+    let raw = b"{\"cmd\":\"report\",\"model\":\"magnet\",\"sid\":\"158d0001a2b3c4\",\"short_id\":4343,\"data\":\"{\\\"status\\\":\\\"open\\\"}\"}";
+    let msg = Message::parse(raw).unwrap();
+    assert_eq!(msg.cmd(),"report");
+    assert_eq!(Message::parse(&msg.to_bytes()).unwrap(),msg);
+}
+
+#[test]
+fn keep_unknown(){
+    let raw = b"{\"cmd\":\"reboot\",\"sid\":\"f0b429cc1234\"}";
+    match Message::parse(raw).unwrap() {
+        Message::Unknown{cmd,raw:bytes} => {
+            assert_eq!(cmd,"reboot");
+            assert_eq!(bytes,raw.to_vec());
+        }
+        other => panic!("unexpected {:?}",other),
+    }
+    assert!(Message::parse(b"{\"cmd\":\"read\"}").is_err());
+}
+
+#[test]
+fn keep_extra_fields(){
+    let raw = b"{\"cmd\":\"report\",\"model\":\"sensor_new.v9\",\"sid\":\"158d0001a2b3c4\",\"rssi\":-60,\"data\":\"{\\\"lux\\\":\\\"120\\\"}\"}";
+    let msg = Message::parse(raw).unwrap();
+    match &msg {
+        Message::Report(frame) => {
+            assert_eq!(frame.model.as_ref().unwrap(),"sensor_new.v9");
+            assert_eq!(frame.extra["rssi"],-60);
+            assert_eq!(frame.data.get_i64("lux"),Some(120));
+        }
+        other => panic!("unexpected {:?}",other),
+    }
+    let value = json::parse(std::str::from_utf8(&msg.to_bytes()).unwrap()).unwrap();
+    assert_eq!(value["rssi"],-60);
+}
+
+#[test]
+fn keep_extra_request_fields(){
+    for raw in [
+        r#"{"cmd":"whois","rssi":-60}"#,
+        r#"{"cmd":"get_id_list","rssi":-60}"#,
+        r#"{"cmd":"read","sid":"158d0001a2b3c4","rssi":-60}"#,
+    ].iter() {
+        let msg = Message::parse(raw.as_bytes()).unwrap();
+        let value = json::parse(std::str::from_utf8(&msg.to_bytes()).unwrap()).unwrap();
+        assert_eq!(value["rssi"],-60,"{}",raw);
+    }
+    assert_eq!(Message::parse(br#"{"cmd":"read","sid":"158d0001a2b3c4"}"#).unwrap(),Message::read("158d0001a2b3c4".parse().unwrap()));
+}
+
+#[test]
+fn decode_data(){
+    let raw = b"{\"cmd\":\"read_ack\",\"model\":\"sensor_ht\",\"sid\":\"158d0001a2b3c4\",\"short_id\":20712,\"data\":\"{\\\"voltage\\\":3015,\\\"temperature\\\":\\\"1799\\\",\\\"humidity\\\":\\\"5206\\\"}\"}";
+    match Message::parse(raw).unwrap() {
+        Message::ReadAck(frame) => {
+            assert_eq!(frame.data.voltage(),Some(3015));
+            assert_eq!(frame.data.temperature(),Some(1799));
+            assert_eq!(frame.data.humidity(),Some(5206));
+        }
+        other => panic!("unexpected {:?}",other),
+    }
+}
+
+#[test]
+fn reject_malformed_data(){
+    let raw = b"{\"cmd\":\"report\",\"model\":\"magnet\",\"sid\":\"158d0001a2b3c4\",\"data\":\"{\\\"status\\\":\"}";
+    match Message::parse(raw) {
+        Err(ProtocolError::InvalidData(_)) => {},
+        other => panic!("unexpected {:?}",other),
+    }
+}
+
+#[test]
+fn parse_v2(){
+    let raw = b"{\"cmd\":\"report\",\"model\":\"sensor_ht.v1\",\"sid\":\"158d0001a2b3c4\",\"params\":[{\"temperature\":2350},{\"humidity\":4512}]}";
+    match Message::parse(raw).unwrap() {
+        Message::Report(frame) => {
+            assert_eq!(frame.data.temperature(),Some(2350));
+            assert_eq!(frame.data.humidity(),Some(4512));
+        }
+        other => panic!("unexpected {:?}",other),
+    }
+
+    let raw = b"{\"cmd\":\"discovery_rsp\",\"sid\":\"f0b429cc1234\",\"token\":\"1234567890abcdef\",\"dev_list\":[{\"sid\":\"158d0001a2b3c4\",\"model\":\"sensor_ht.v1\"}]}";
+    match Message::parse(raw).unwrap() {
+        Message::GetIdListAck(list) => assert_eq!(list.devices,vec!["158d0001a2b3c4"]),
+        other => panic!("unexpected {:?}",other),
+    }
+}
+
+#[test]
+fn encode_v2(){
+    let raw = b"{\"cmd\":\"write\",\"model\":\"ctrl_neutral1\",\"sid\":\"158d0001a2b3c4\",\"data\":{\"channel_0\":\"on\",\"key\":\"3EB43E37C20AFF4C5872CC0D04D81314\"}}";
+    let msg = Message::parse(raw).unwrap();
+    let v2 = json::parse(std::str::from_utf8(&msg.to_bytes_with(Version::V2)).unwrap()).unwrap();
+    assert_eq!(v2["key"],"3EB43E37C20AFF4C5872CC0D04D81314");
+    assert_eq!(v2["params"][0]["channel_0"],"on");
+    assert_eq!(Message::parse(v2.dump().as_bytes()).unwrap(),msg);
+}
+
+#[test]
+fn sign_write(){
+    let command = WriteCommand::new("158d0001a2b3c4".parse().unwrap(),"plug".parse().unwrap()).set("status","on");
+    let msg = command.sign("0987654321qwerty",&"1234567890abcdef".parse().unwrap()).unwrap();
+    let value = json::parse(std::str::from_utf8(&msg.to_bytes()).unwrap()).unwrap();
+    assert_eq!(value["cmd"],"write");
+    assert_eq!(value["model"],"plug");
+    assert_eq!(value["data"]["status"],"on");
+    assert_eq!(value["data"]["key"],"3EB43E37C20AFF4C5872CC0D04D81314");
+}
+
+#[test]
+fn ack_error(){
+    let raw = b"{\"cmd\":\"write_ack\",\"model\":\"plug\",\"sid\":\"158d0001a2b3c4\",\"data\":\"{\\\"error\\\":\\\"Invalid key\\\"}\"}";
+    match Message::parse(raw).unwrap() {
+        Message::WriteAck(frame) => assert_eq!(frame.error(),Some(AckError::InvalidKey)),
+        other => panic!("unexpected {:?}",other),
+    }
+    assert_eq!(AckError::from("Invalid device"),AckError::InvalidDevice);
+    assert_eq!(AckError::from("Unknown cmd"),AckError::Other("Unknown cmd".to_string()));
+}
@@ -36,11 +36,11 @@ fn unicast() ->Res<()>{
     // 测试单播发送数据
 
     // 初始化单播客户端
-    let client = Unicast::connect(server_address.clone(),server_port)?;
+    let client = Unicast::connect(server_address,server_port)?;
 
     // 发送数据
     let message = "{ \"cmd\": \"unicast\" }";
-    client.send(&message.as_bytes())?;
+    client.send(message.as_bytes())?;
 
     // 接收数据
     let mut buffer = [0;1024];
@@ -91,7 +91,7 @@ fn broadcast()->Res<()>{
 
     // 发送数据
     let message = "{ \"cmd\": \"broadcast\" }";
-    client.send(&message.as_bytes())?;
+    client.send(message.as_bytes())?;
 
     // 接收数据
     let mut buffer = [0;1024];
@@ -145,7 +145,7 @@ fn multicast()->Res<()>{
 
     // 发送数据
     let message = "{ \"cmd\": \"multicast\" }";
-    client.send(&message.as_bytes())?;
+    client.send(message.as_bytes())?;
 
     // 接收数据
     let mut buffer = [0;1024];
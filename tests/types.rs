@@ -0,0 +1,41 @@
+use aqara_rs::types::{Model, ShortId, Sid, Token};
+use std::collections::HashMap;
+
+#[test]
+fn sid_works(){
+    let gateway:Sid = "f0b429cc1234".parse().unwrap();
+    let device:Sid = "158d0001a2b3c4".parse().unwrap();
+    let v2:Sid = "lumi.158d0001a2b3c4".parse().unwrap();
+    assert_eq!(v2.as_str(),"lumi.158d0001a2b3c4");
+    assert!("".parse::<Sid>().is_err());
+    assert!("158d0001a2b3c4ff00".parse::<Sid>().is_err());
+
+    let mut names = HashMap::new();
+    names.insert(gateway.clone(),"gateway");
+    names.insert(device,"sensor");
+    assert_eq!(names.get(&"F0B429CC1234".parse::<Sid>().unwrap()),Some(&"gateway"));
+}
+
+#[test]
+fn short_id_works(){
+    assert_eq!("20712".parse::<ShortId>().unwrap(),ShortId(20712));
+    assert!("70000".parse::<ShortId>().is_err());
+}
+
+#[test]
+fn model_works(){
+    let model:Model = "weather.v1".parse().unwrap();
+    assert_eq!(model,"weather.v1");
+    assert!(!model.is_gateway());
+    assert!("gateway.aq1".parse::<Model>().unwrap().is_gateway());
+    assert!("bad model".parse::<Model>().is_err());
+}
+
+#[test]
+fn token_works(){
+    let token:Token = "1234567890abcdef".parse().unwrap();
+    assert_eq!(token.to_string(),"1234567890abcdef");
+    assert_eq!(token.as_bytes().len(),16);
+    assert!("1234567890abcdefg".parse::<Token>().is_err());
+    assert!("1234567890abcdé".parse::<Token>().is_err());
+}